@@ -1,5 +1,6 @@
-use criterion::{criterion_group, criterion_main, Criterion};
-use AoC2024::{day0, day10, day11, day12, day13, day2, day3, day4, day5, day6, day7, day8, day9};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use AoC2024::day11::evolution::{Evolution, PUZZLE_RULES, STRING_SPLIT_RULES};
+use AoC2024::{day0, day10, day11, day12, day13, day14, day2, day3, day4, day5, day6, day7, day8, day9};
 
 fn day0(c: &mut Criterion) {
     c.bench_function("day 0 part1", |b| b.iter(day0::part1));
@@ -47,6 +48,17 @@ fn day10(c: &mut Criterion) {
 fn day11(c: &mut Criterion) {
     c.bench_function("day 11 part1", |b| b.iter(day11::part1));
     c.bench_function("day 11 part2", |b| b.iter(day11::part2));
+
+    // Digit splitting by arithmetic against formatting and parsing, with a fresh memo every time
+    let mut group = c.benchmark_group("day 11 blinks");
+    for blinks in [75, 200] {
+        for (name, rules) in [("arithmetic", PUZZLE_RULES), ("string", STRING_SPLIT_RULES)] {
+            group.bench_with_input(BenchmarkId::new(name, blinks), &blinks, |b, blinks| {
+                b.iter(|| Evolution::new(rules.to_vec()).count_all(&[125, 17], *blinks))
+            });
+        }
+    }
+    group.finish();
 }
 fn day12(c: &mut Criterion) {
     c.bench_function("day 12 part1", |b| b.iter(day12::part1));
@@ -61,5 +73,5 @@ fn day14(c: &mut Criterion) {
     c.bench_function("day 14 part2", |b| b.iter(day14::part2));
 }
 
-criterion_group!(benches, day0, day1, day2, day3, day4, day5, day6, day7, day8, day9, day10, day11, day12, day13);
+criterion_group!(benches, day0, day1, day2, day3, day4, day5, day6, day7, day8, day9, day10, day11, day12, day13, day14);
 criterion_main!(benches);
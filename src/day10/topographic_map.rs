@@ -0,0 +1,365 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::str::FromStr;
+
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, Ord, PartialOrd)]
+pub struct Pos {
+    pub x: usize,
+    pub y: usize
+}
+impl From<(usize, usize)> for Pos {
+    fn from((x, y): (usize, usize)) -> Pos {
+        Pos{x, y}
+    }
+}
+
+/// Heights of every cell, row by row. Impassable cells, drawn as `.`, are None
+pub struct TopographicMap {
+    heights: Vec<Option<u8>>,
+    width: usize,
+    height: usize,
+}
+impl FromStr for TopographicMap {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<&str> = s.split_whitespace().collect();
+        let width = lines.first().ok_or(())?.chars().count();
+        if lines.iter().any(|line| line.chars().count() != width) {
+            return Err(());
+        }
+        let heights = lines.iter()
+            .flat_map(|line| line.chars())
+            .map(|c| match c {
+                '.' => Ok(None),
+                _ => c.to_digit(10).map(|d| Some(d as u8)).ok_or(()),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(TopographicMap { heights, width, height: lines.len() })
+    }
+}
+impl TopographicMap {
+    fn as_pos(&self, index: usize) -> Pos {
+        Pos { x: index % self.width, y: index / self.width }
+    }
+    fn neighbors(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        let Pos { x, y } = self.as_pos(index);
+        [
+            (y + 1 < self.height).then(|| index + self.width),
+            (x + 1 < self.width).then(|| index + 1),
+            (y > 0).then(|| index - self.width),
+            (x > 0).then(|| index - 1),
+        ].into_iter().flatten()
+    }
+}
+
+/// Whether a trail may step from a cell of the first height onto a neighbour of the second height
+pub type StepRule = fn(from: u8, to: u8) -> bool;
+/// Exactly one higher, as in the puzzle
+pub const STEP_UP: StepRule = |from, to| to == from + 1;
+/// One higher or one lower. Allows circles, see [TrailGraph::with_rules]
+pub const STEP_UP_OR_DOWN: StepRule = |from, to| to.abs_diff(from) == 1;
+/// At most one higher, flat or any amount lower. Allows circles, see [TrailGraph::with_rules]
+pub const STEP_AT_MOST_UP: StepRule = |from, to| to <= from + 1;
+
+/// What makes a trail: where it starts and ends and how it may go up and down in between
+#[derive(Clone, Copy)]
+pub struct TrailRules {
+    pub start: u8,
+    pub end: u8,
+    pub step: StepRule,
+}
+impl Default for TrailRules {
+    fn default() -> Self {
+        TrailRules { start: 0, end: 9, step: STEP_UP }
+    }
+}
+
+/// Per trailhead results
+#[derive(Debug, Eq, PartialEq)]
+pub struct Trailhead {
+    /// Every summit that can be reached from the trailhead
+    pub summits: HashSet<Pos>,
+    /// How many distinct trails lead from the trailhead to any summit
+    pub rating: usize,
+}
+impl Trailhead {
+    pub fn score(&self) -> usize {
+        self.summits.len()
+    }
+}
+
+/// Every allowed step on a [TopographicMap], built once so that trails can be followed
+/// without looking at the heights again.
+///
+/// Trails end as soon as they reach a summit, and never visit a cell twice
+pub struct TrailGraph {
+    map: TopographicMap,
+    rules: TrailRules,
+    /// For every cell, the cells a trail can step onto from it
+    steps: Vec<Vec<usize>>,
+}
+impl TrailGraph {
+    pub fn new(map: TopographicMap) -> TrailGraph {
+        TrailGraph::with_rules(map, TrailRules::default())
+    }
+    /// Step rules that allow going back to a height already visited, such as [STEP_UP_OR_DOWN]
+    /// and [STEP_AT_MOST_UP], let trails go round in circles. Trails still never visit a cell
+    /// twice, but [TrailGraph::trailheads] then has to walk every simple path from each
+    /// trailhead, which takes exponential time in the size of the map. Keep such maps small
+    pub fn with_rules(map: TopographicMap, rules: TrailRules) -> TrailGraph {
+        let steps = (0..map.heights.len())
+            .map(|index| match map.heights[index] {
+                Some(h) if h != rules.end => map.neighbors(index)
+                    .filter(|n| map.heights[*n].is_some_and(|to| (rules.step)(h, to)))
+                    .collect(),
+                _ => Vec::new(),
+            })
+            .collect();
+        TrailGraph { map, rules, steps }
+    }
+
+    fn is_trailhead(&self, index: usize) -> bool {
+        self.map.heights[index] == Some(self.rules.start)
+    }
+    fn is_summit(&self, index: usize) -> bool {
+        self.map.heights[index] == Some(self.rules.end)
+    }
+
+    /// Cells ordered so that every step goes to a cell later in the order,
+    /// or None if the steps allow going round in circles
+    fn topological_order(&self) -> Option<Vec<usize>> {
+        let mut incoming = vec![0usize; self.steps.len()];
+        self.steps.iter().flatten().for_each(|to| incoming[*to] += 1);
+        let mut queue: VecDeque<usize> = (0..incoming.len()).filter(|i| incoming[*i] == 0).collect();
+        let mut order = Vec::with_capacity(incoming.len());
+        while let Some(index) = queue.pop_front() {
+            order.push(index);
+            for next in self.steps[index].iter() {
+                incoming[*next] -= 1;
+                if incoming[*next] == 0 {
+                    queue.push_back(*next);
+                }
+            }
+        }
+        (order.len() == incoming.len()).then_some(order)
+    }
+
+    /// Reachable summits and number of trails for every trailhead.
+    ///
+    /// When trails can't go round in circles this works back from the summits so that every cell
+    /// is only evaluated once. Otherwise every trail has to be walked separately, which is
+    /// exponential in the size of the map
+    pub fn trailheads(&self) -> HashMap<Pos, Trailhead> {
+        let cells = self.steps.len();
+        let trailheads = (0..cells).filter(|index| self.is_trailhead(*index));
+        let Some(order) = self.topological_order() else {
+            return trailheads
+                .map(|index| {
+                    let mut summits = HashSet::new();
+                    let mut rating = 0;
+                    self.walk_trails(&mut vec![index], &mut |trail| {
+                        summits.insert(self.map.as_pos(*trail.last().unwrap()));
+                        rating += 1;
+                    });
+                    (self.map.as_pos(index), Trailhead { summits, rating })
+                })
+                .collect();
+        };
+
+        let mut summits: Vec<HashSet<usize>> = vec![HashSet::new(); cells];
+        let mut paths: Vec<usize> = vec![0; cells];
+        for index in order.into_iter().rev() {
+            if self.is_summit(index) {
+                summits[index].insert(index);
+                paths[index] = 1;
+            }
+            for next in self.steps[index].iter() {
+                let reachable = summits[*next].clone();
+                summits[index].extend(reachable);
+                paths[index] += paths[*next];
+            }
+        }
+        trailheads
+            .map(|index| (self.map.as_pos(index), Trailhead {
+                summits: summits[index].iter().map(|s| self.map.as_pos(*s)).collect(),
+                rating: paths[index],
+            }))
+            .collect()
+    }
+
+    /// Every trail from the given trailhead to a summit, as the list of cells along it
+    pub fn trails(&self, trailhead: Pos) -> Vec<Vec<Pos>> {
+        if trailhead.x >= self.map.width || trailhead.y >= self.map.height {
+            return Vec::new();
+        }
+        let start = trailhead.y * self.map.width + trailhead.x;
+        if !self.is_trailhead(start) {
+            return Vec::new();
+        }
+        let mut trails = Vec::new();
+        self.walk_trails(&mut vec![start], &mut |trail| {
+            trails.push(trail.iter().map(|i| self.map.as_pos(*i)).collect())
+        });
+        trails
+    }
+    /// Calls `on_trail` with every trail that continues the given partial trail
+    fn walk_trails(&self, trail: &mut Vec<usize>, on_trail: &mut impl FnMut(&[usize])) {
+        let last = *trail.last().expect("Trails start at the trailhead");
+        if self.is_summit(last) {
+            on_trail(trail);
+        }
+        for next in self.steps[last].iter() {
+            if trail.contains(next) {
+                continue;
+            }
+            trail.push(*next);
+            self.walk_trails(trail, on_trail);
+            trail.pop();
+        }
+    }
+
+    pub fn score(&self) -> usize {
+        self.trailheads().values().map(Trailhead::score).sum()
+    }
+    pub fn rating(&self) -> usize {
+        self.trailheads().values().map(|t| t.rating).sum()
+    }
+}
+
+#[cfg(test)]
+const DEMO: &str = "\
+89010123
+78121874
+87430965
+96549874
+45678903
+32019012
+01329801
+10456732
+";
+
+#[test]
+fn test_demo_per_trailhead() {
+    let graph = TrailGraph::new(DEMO.parse().expect("Demo should parse"));
+    let trailheads = graph.trailheads();
+    assert_eq!(trailheads.len(), 9);
+    assert_eq!(graph.score(), 36);
+    assert_eq!(graph.rating(), 81);
+
+    let mut scores: Vec<(Pos, usize)> = trailheads.iter().map(|(pos, t)| (*pos, t.score())).collect();
+    scores.sort_by_key(|(pos, _)| (pos.y, pos.x));
+    assert_eq!(scores.iter().map(|(_, s)| *s).collect::<Vec<_>>(), vec![5, 6, 5, 3, 1, 3, 5, 3, 5]);
+    let mut ratings: Vec<(Pos, usize)> = trailheads.iter().map(|(pos, t)| (*pos, t.rating)).collect();
+    ratings.sort_by_key(|(pos, _)| (pos.y, pos.x));
+    assert_eq!(ratings.iter().map(|(_, r)| *r).collect::<Vec<_>>(), vec![20, 24, 10, 4, 1, 4, 5, 8, 5]);
+}
+
+#[test]
+fn test_enumerate_trails() {
+    let graph = TrailGraph::new("\
+0123
+1234
+8765
+9876
+".parse().expect("Map should parse"));
+    let trails = graph.trails(Pos { x: 0, y: 0 });
+    assert_eq!(graph.trailheads()[&Pos { x: 0, y: 0 }].rating, trails.len());
+    assert_eq!(trails.len(), 16);
+    for trail in trails.iter() {
+        assert_eq!(trail.len(), 10);
+        assert_eq!(trail.first(), Some(&Pos { x: 0, y: 0 }));
+        assert_eq!(trail.last(), Some(&Pos { x: 0, y: 3 }));
+    }
+    let unique: HashSet<&Vec<Pos>> = trails.iter().collect();
+    assert_eq!(unique.len(), trails.len());
+    assert!(graph.trails(Pos { x: 1, y: 0 }).is_empty());
+    // Off the map on either axis
+    assert!(graph.trails(Pos { x: 4, y: 0 }).is_empty());
+    assert!(graph.trails(Pos { x: 0, y: 4 }).is_empty());
+    assert!(graph.trails(Pos { x: usize::MAX, y: usize::MAX }).is_empty());
+}
+
+#[test]
+fn test_impassable_tiles() {
+    let graph = TrailGraph::new("\
+...0...
+...1...
+...2...
+6543456
+7.....7
+8.....8
+9.....9
+".parse().expect("Map should parse"));
+    assert_eq!(graph.score(), 2);
+
+    let graph = TrailGraph::new("\
+..90..9
+...1.98
+...2..7
+6543456
+765.987
+876....
+987....
+".parse().expect("Map should parse"));
+    assert_eq!(graph.score(), 4);
+
+    let graph = TrailGraph::new("\
+.....0.
+..4321.
+..5..2.
+..6543.
+..7..4.
+..8765.
+..9....
+".parse().expect("Map should parse"));
+    assert_eq!(graph.rating(), 3);
+
+    assert!("01x\n234".parse::<TopographicMap>().is_err());
+}
+
+#[test]
+fn test_step_rules() {
+    let map = || "01.2\n.101".parse::<TopographicMap>().expect("Map should parse");
+    let rules = |step| TrailRules { start: 0, end: 2, step };
+    let ratings = |graph: TrailGraph| {
+        let mut ratings: Vec<(Pos, usize, usize)> = graph.trailheads().into_iter()
+            .map(|(pos, t)| (pos, t.score(), t.rating))
+            .collect();
+        ratings.sort();
+        ratings
+    };
+
+    // (0, 0) is a dead end going strictly up, (2, 1) climbs to the summit on the right
+    assert_eq!(ratings(TrailGraph::with_rules(map(), rules(STEP_UP))), vec![
+        (Pos { x: 0, y: 0 }, 0, 0),
+        (Pos { x: 2, y: 1 }, 1, 1),
+    ]);
+    // Stepping down doesn't help, the two 1s next to each other are still in the way
+    assert_eq!(ratings(TrailGraph::with_rules(map(), rules(STEP_UP_OR_DOWN))), vec![
+        (Pos { x: 0, y: 0 }, 0, 0),
+        (Pos { x: 2, y: 1 }, 1, 1),
+    ]);
+    // Flat steps connect them, and the trail from (0, 0) can drop back down to 0 on its way
+    assert_eq!(ratings(TrailGraph::with_rules(map(), rules(STEP_AT_MOST_UP))), vec![
+        (Pos { x: 0, y: 0 }, 1, 1),
+        (Pos { x: 2, y: 1 }, 1, 1),
+    ]);
+    let graph = TrailGraph::with_rules(map(), rules(STEP_AT_MOST_UP));
+    assert_eq!(graph.trails(Pos { x: 0, y: 0 }), vec![vec![
+        Pos { x: 0, y: 0 }, Pos { x: 1, y: 0 }, Pos { x: 1, y: 1 }, Pos { x: 2, y: 1 }, Pos { x: 3, y: 1 }, Pos { x: 3, y: 0 },
+    ]]);
+}
+
+#[test]
+fn test_different_start_and_end() {
+    // Only the upper half of the demo, from 5 to 9
+    let graph = TrailGraph::with_rules(DEMO.parse().expect("Demo should parse"), TrailRules { start: 5, end: 9, step: STEP_UP });
+    let height = |pos: &Pos| DEMO.lines().nth(pos.y).unwrap().as_bytes()[pos.x];
+    let trailheads = graph.trailheads();
+    assert!(trailheads.keys().all(|pos| height(pos) == b'5'));
+    for (start, trailhead) in trailheads {
+        let trails = graph.trails(start);
+        assert_eq!(trails.len(), trailhead.rating);
+        assert!(trails.iter().all(|trail| trail.iter().map(height).eq(b'5'..=b'9')));
+    }
+}
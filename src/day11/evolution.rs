@@ -0,0 +1,290 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// One way a stone can change when blinking. The first rule whose predicate matches is applied.
+/// The transform returns None if a new stone wouldn't fit in a usize
+#[derive(Clone, Copy)]
+pub struct Rule {
+    pub predicate: fn(usize) -> bool,
+    pub transform: fn(usize) -> Option<Vec<usize>>,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Overflow {
+    /// Blinking this stone would produce a stone too large for a usize
+    Stone(usize),
+    /// There are more stones than fit in a u128
+    Count,
+}
+
+/// Number of decimal digits, 0 has one digit
+pub fn digit_count(n: usize) -> u32 {
+    n.checked_ilog10().map_or(1, |log| log + 1)
+}
+/// The left and right half of the digits, e.g. 1234 -> (12, 34) and 1000 -> (10, 0)
+pub fn split_digits(n: usize) -> (usize, usize) {
+    let half = 10usize.pow(digit_count(n) / 2);
+    (n / half, n % half)
+}
+
+/// The three rules from the puzzle, in the order they have to be checked
+pub const PUZZLE_RULES: [Rule; 3] = [
+    // 0 becomes 1
+    Rule { predicate: |stone| stone == 0, transform: |_| Some(vec![1]) },
+    // An even number of digits splits into the left and right halves
+    Rule {
+        predicate: |stone| digit_count(stone).is_multiple_of(2),
+        transform: |stone| {
+            let (left, right) = split_digits(stone);
+            Some(vec![left, right])
+        },
+    },
+    // Anything else is multiplied by 2024
+    Rule { predicate: |_| true, transform: |stone| stone.checked_mul(2024).map(|product| vec![product]) },
+];
+
+/// [PUZZLE_RULES] with the digits split by formatting and parsing the stone, to benchmark against
+pub const STRING_SPLIT_RULES: [Rule; 3] = [
+    PUZZLE_RULES[0],
+    Rule {
+        predicate: |stone| stone.to_string().len() % 2 == 0,
+        transform: |stone| {
+            let str = stone.to_string();
+            let (left, right) = str.split_at(str.len() / 2);
+            Some(vec![left.parse().unwrap(), right.parse().unwrap()])
+        },
+    },
+    PUZZLE_RULES[2],
+];
+
+/// Evolves a multiset of stones according to a list of rules.
+///
+/// Stones never influence each other, so the number of stones a single stone turns into after a
+/// number of blinks only depends on its value. These counts are remembered across calls.
+///
+/// Counts are kept as u128, which is enough for a few hundred blinks
+pub struct Evolution {
+    rules: Vec<Rule>,
+    /// (stone, blinks) -> number of stones after that many blinks
+    memo: HashMap<(usize, usize), u128>,
+}
+
+impl Evolution {
+    pub fn new(rules: Vec<Rule>) -> Evolution {
+        Evolution { rules, memo: HashMap::new() }
+    }
+    pub fn puzzle() -> Evolution {
+        Evolution::new(PUZZLE_RULES.to_vec())
+    }
+
+    /// What a single stone turns into after one blink. Stones no rule matches stay as they are
+    pub fn blink(&self, stone: usize) -> Result<Vec<usize>, Overflow> {
+        self.rules.iter()
+            .find(|rule| (rule.predicate)(stone))
+            .map_or(Some(vec![stone]), |rule| (rule.transform)(stone))
+            .ok_or(Overflow::Stone(stone))
+    }
+
+    /// Number of stones a single stone turns into after blinking `blinks` times
+    pub fn count(&mut self, stone: usize, blinks: usize) -> Result<u128, Overflow> {
+        if blinks == 0 {
+            return Ok(1);
+        }
+        if let Some(count) = self.memo.get(&(stone, blinks)) {
+            return Ok(*count);
+        }
+        let mut count: u128 = 0;
+        for product in self.blink(stone)? {
+            count = count.checked_add(self.count(product, blinks - 1)?).ok_or(Overflow::Count)?;
+        }
+        self.memo.insert((stone, blinks), count);
+        Ok(count)
+    }
+
+    pub fn count_all(&mut self, stones: &[usize], blinks: usize) -> Result<u128, Overflow> {
+        stones.iter().try_fold(0u128, |total, stone| {
+            total.checked_add(self.count(*stone, blinks)?).ok_or(Overflow::Count)
+        })
+    }
+
+    /// Every value that can ever appear when starting from the given stones, or None if there are
+    /// more than `limit` of them or one of them overflows. The set is closed: blinking any value
+    /// in it only produces values in it again
+    pub fn distinct_values(&self, stones: &[usize], limit: usize) -> Option<HashSet<usize>> {
+        let mut seen: HashSet<usize> = stones.iter().copied().collect();
+        let mut queue: VecDeque<usize> = seen.iter().copied().collect();
+        while let Some(stone) = queue.pop_front() {
+            for product in self.blink(stone).ok()? {
+                if seen.insert(product) {
+                    if seen.len() > limit {
+                        return None;
+                    }
+                    queue.push_back(product);
+                }
+            }
+        }
+        (seen.len() <= limit).then_some(seen)
+    }
+
+    /// Number of stones after blinking `blinks` times, modulo `modulus`.
+    ///
+    /// The count grows exponentially, so for very many blinks only the remainder can be stored.
+    /// Blinking is a linear map on the counts of each value in [Evolution::distinct_values], so this
+    /// raises that transition matrix to the power `blinks` by repeated squaring.
+    /// That takes O(n³ log blinks) for n distinct values, or None if there are more than `limit`
+    pub fn count_mod(&self, stones: &[usize], blinks: u64, modulus: u64, limit: usize) -> Option<u64> {
+        let mut values: Vec<usize> = self.distinct_values(stones, limit)?.into_iter().collect();
+        values.sort();
+        let index: HashMap<usize, usize> = values.iter().enumerate().map(|(i, v)| (*v, i)).collect();
+
+        let mut transitions = Matrix::zero(values.len());
+        for (from, value) in values.iter().enumerate() {
+            for product in self.blink(*value).ok()? {
+                let to = index[&product];
+                transitions.set(from, to, (transitions.get(from, to) + 1) % modulus);
+            }
+        }
+        let mut counts = vec![0u64; values.len()];
+        for stone in stones {
+            counts[index[stone]] = (counts[index[stone]] + 1) % modulus;
+        }
+
+        let mut power = blinks;
+        while power > 0 {
+            if power & 1 == 1 {
+                counts = transitions.apply(&counts, modulus);
+            }
+            power >>= 1;
+            if power > 0 {
+                transitions = transitions.square(modulus);
+            }
+        }
+        Some(counts.iter().fold(0, |total, count| (total + count) % modulus))
+    }
+}
+
+/// Square matrix, stored row by row
+struct Matrix {
+    size: usize,
+    cells: Vec<u64>,
+}
+impl Matrix {
+    fn zero(size: usize) -> Matrix {
+        Matrix { size, cells: vec![0; size * size] }
+    }
+    fn get(&self, row: usize, col: usize) -> u64 {
+        self.cells[row * self.size + col]
+    }
+    fn set(&mut self, row: usize, col: usize, value: u64) {
+        self.cells[row * self.size + col] = value;
+    }
+    /// The row vector `counts` times this matrix
+    fn apply(&self, counts: &[u64], modulus: u64) -> Vec<u64> {
+        let mut result = vec![0u64; self.size];
+        for (row, count) in counts.iter().enumerate().filter(|(_, count)| **count != 0) {
+            for (col, value) in result.iter_mut().enumerate() {
+                let product = *count as u128 * self.get(row, col) as u128;
+                *value = ((*value as u128 + product) % modulus as u128) as u64;
+            }
+        }
+        result
+    }
+    fn square(&self, modulus: u64) -> Matrix {
+        let mut result = Matrix::zero(self.size);
+        for row in 0..self.size {
+            let row_values = &self.cells[row * self.size..(row + 1) * self.size];
+            result.cells[row * self.size..(row + 1) * self.size].copy_from_slice(&self.apply(row_values, modulus));
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+const DEMO: [usize; 2] = [125, 17];
+
+#[test]
+fn test_blink() {
+    let evolution = Evolution::puzzle();
+    let blinked: Vec<usize> = [0, 1, 10, 99, 999].iter().flat_map(|s| evolution.blink(*s).unwrap()).collect();
+    assert_eq!(blinked, vec![1, 2024, 1, 0, 9, 9, 2021976]);
+}
+
+#[test]
+fn test_split_digits() {
+    assert_eq!(digit_count(0), 1);
+    assert_eq!(digit_count(9), 1);
+    assert_eq!(digit_count(10), 2);
+    assert_eq!(digit_count(usize::MAX), usize::MAX.to_string().len() as u32);
+    assert_eq!(split_digits(1234), (12, 34));
+    assert_eq!(split_digits(1000), (10, 0));
+    assert_eq!(split_digits(99), (9, 9));
+
+    // Same stones as splitting the decimal string, including right at the edges of each power of ten
+    let string_rules = Evolution::new(STRING_SPLIT_RULES.to_vec());
+    let arithmetic_rules = Evolution::puzzle();
+    let mut stone = 1usize;
+    while let Some(next) = stone.checked_mul(10) {
+        for n in [stone - 1, stone, stone + 1, next - 1] {
+            assert_eq!(arithmetic_rules.blink(n), string_rules.blink(n), "Stone {n}");
+        }
+        stone = next;
+    }
+}
+
+#[test]
+fn test_overflow() {
+    let mut evolution = Evolution::puzzle();
+    // An odd number of digits close to the limit can't be multiplied by 2024
+    let huge = usize::MAX / 1000;
+    assert!(!digit_count(huge).is_multiple_of(2));
+    assert_eq!(evolution.blink(huge), Err(Overflow::Stone(huge)));
+    assert_eq!(evolution.count(huge, 1), Err(Overflow::Stone(huge)));
+
+    // Stones that double forever run out of room after 128 blinks
+    let mut doubling = Evolution::new(vec![Rule { predicate: |_| true, transform: |stone| Some(vec![stone, stone]) }]);
+    assert_eq!(doubling.count(1, 127), Ok(1 << 127));
+    assert_eq!(doubling.count(1, 128), Err(Overflow::Count));
+
+    // The puzzle itself fits comfortably in u128 for 200 blinks
+    assert!(evolution.count_all(&DEMO, 200).is_ok());
+}
+
+#[test]
+fn test_demo() {
+    let mut evolution = Evolution::puzzle();
+    assert_eq!(evolution.count_all(&DEMO, 6), Ok(22));
+    assert_eq!(evolution.count_all(&DEMO, 25), Ok(55312));
+}
+
+#[test]
+fn test_custom_rules() {
+    // Every stone splits into two smaller ones until it reaches 0, which stays
+    let mut evolution = Evolution::new(vec![
+        Rule { predicate: |stone| stone > 0, transform: |stone| Some(vec![stone - 1, stone - 1]) },
+    ]);
+    assert_eq!(evolution.blink(0), Ok(vec![0]));
+    assert_eq!(evolution.count(3, 2), Ok(4));
+    assert_eq!(evolution.count(3, 10), Ok(8));
+    assert_eq!(evolution.distinct_values(&[3], 10), Some(HashSet::from([0, 1, 2, 3])));
+    assert_eq!(evolution.distinct_values(&[3], 3), None);
+}
+
+#[test]
+fn test_distinct_values_are_closed() {
+    let evolution = Evolution::puzzle();
+    let values = evolution.distinct_values(&DEMO, 10_000).expect("Demo should saturate");
+    assert!(values.contains(&0) && values.contains(&2024) && values.contains(&253000));
+    assert!(values.iter().all(|v| evolution.blink(*v).unwrap().iter().all(|p| values.contains(p))));
+}
+
+#[test]
+fn test_count_mod() {
+    const MODULUS: u64 = 1_000_000_007;
+    let mut evolution = Evolution::puzzle();
+    assert_eq!(evolution.count_mod(&DEMO, 0, MODULUS, 10_000), Some(2));
+    assert_eq!(evolution.count_mod(&DEMO, 25, MODULUS, 10_000), Some(55312));
+    let expected = (evolution.count_all(&DEMO, 75).unwrap() % MODULUS as u128) as u64;
+    assert_eq!(evolution.count_mod(&DEMO, 75, MODULUS, 10_000), Some(expected));
+    // Far more blinks than could ever be simulated
+    assert!(evolution.count_mod(&[0], 1_000_000, MODULUS, 10_000).is_some());
+    assert_eq!(evolution.count_mod(&DEMO, 25, MODULUS, 10), None);
+}
@@ -6,12 +6,15 @@ use nom::multi::separated_list1;
 use nom::{IResult, Parser};
 use crate::utils::read_input_file;
 
+pub mod outline;
+pub mod regions;
+
 const DAY: &str = "day12";
 
 #[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
-struct Pos {
-    x: usize,
-    y: usize
+pub struct Pos {
+    pub x: usize,
+    pub y: usize
 }
 impl Pos {
     fn get_neighbors(&self) -> Vec<Pos> {
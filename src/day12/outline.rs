@@ -0,0 +1,231 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use crate::day12::Pos;
+use crate::day12::regions::{Garden, Region};
+
+/// The boundary of a region as closed polygons. Vertices are lattice corners, so `Pos { x, y }`
+/// is the top left corner of the plot at (x, y), and only corners where the fence turns are kept.
+///
+/// With y pointing down the outer ring runs clockwise and holes run counter-clockwise, so the
+/// region is always on the right. Every ring starts at its top left vertex
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Outline {
+    pub outer: Vec<Pos>,
+    pub holes: Vec<Vec<Pos>>,
+}
+
+/// Directions in clockwise order, so turning right is adding one and turning left is adding three
+const EAST: usize = 0;
+const SOUTH: usize = 1;
+const WEST: usize = 2;
+const NORTH: usize = 3;
+
+fn step(pos: Pos, dir: usize) -> Pos {
+    match dir {
+        EAST => Pos { x: pos.x + 1, y: pos.y },
+        SOUTH => Pos { x: pos.x, y: pos.y + 1 },
+        WEST => Pos { x: pos.x - 1, y: pos.y },
+        _ => Pos { x: pos.x, y: pos.y - 1 },
+    }
+}
+
+impl Region {
+    pub fn outline(&self) -> Outline {
+        let cells: HashSet<Pos> = self.cells.iter().copied().collect();
+        let outside = |x: Option<usize>, y: Option<usize>| match (x, y) {
+            (Some(x), Some(y)) => !cells.contains(&Pos { x, y }),
+            _ => true,
+        };
+        // Every fence segment, as the corner it starts at and the direction it runs in
+        let mut edges: HashMap<Pos, Vec<usize>> = HashMap::new();
+        for Pos { x, y } in self.cells.iter().copied() {
+            let mut add = |start: Pos, dir: usize| edges.entry(start).or_default().push(dir);
+            if outside(Some(x), y.checked_sub(1)) {
+                add(Pos { x, y }, EAST);
+            }
+            if outside(Some(x + 1), Some(y)) {
+                add(Pos { x: x + 1, y }, SOUTH);
+            }
+            if outside(Some(x), Some(y + 1)) {
+                add(Pos { x: x + 1, y: y + 1 }, WEST);
+            }
+            if outside(x.checked_sub(1), Some(y)) {
+                add(Pos { x, y: y + 1 }, NORTH);
+            }
+        }
+
+        let mut outer = None;
+        let mut holes = Vec::new();
+        while let Some(ring) = trace(&mut edges) {
+            if signed_area(&ring) > 0 {
+                outer = Some(ring);
+            } else {
+                holes.push(ring);
+            }
+        }
+        holes.sort_by_key(|ring| (ring[0].y, ring[0].x));
+        Outline { outer: outer.expect("Every region has an outer boundary"), holes }
+    }
+}
+
+/// Follows fence segments from the top left one that's left until getting back to it, and
+/// returns the corners along the way.
+///
+/// Where two fences meet in a single corner, the region's plots only touch diagonally there, and
+/// so do the plots outside it. Turning left crosses over to the other plot of the region, which
+/// keeps the plots outside apart. Plots enclosed that way are then traced as separate holes,
+/// the same way [Region::holes] groups them
+fn trace(edges: &mut HashMap<Pos, Vec<usize>>) -> Option<Vec<Pos>> {
+    let start = *edges.keys().min_by_key(|pos| (pos.y, pos.x))?;
+    let start_dir = *edges[&start].iter().min().unwrap();
+    take(edges, start, start_dir);
+
+    let mut ring = Vec::new();
+    let (mut pos, mut dir) = (step(start, start_dir), start_dir);
+    loop {
+        let next = [(dir + 3) % 4, dir, (dir + 1) % 4].into_iter()
+            .find(|d| (pos == start && *d == start_dir) || edges.get(&pos).is_some_and(|dirs| dirs.contains(d)))
+            .expect("Fences form closed loops");
+        if next != dir {
+            ring.push(pos);
+        }
+        if pos == start && next == start_dir {
+            break;
+        }
+        take(edges, pos, next);
+        pos = step(pos, next);
+        dir = next;
+    }
+    let first = (0..ring.len()).min_by_key(|i| (ring[*i].y, ring[*i].x)).unwrap();
+    ring.rotate_left(first);
+    Some(ring)
+}
+
+fn take(edges: &mut HashMap<Pos, Vec<usize>>, pos: Pos, dir: usize) {
+    let dirs = edges.get_mut(&pos).unwrap();
+    dirs.retain(|d| *d != dir);
+    if dirs.is_empty() {
+        edges.remove(&pos);
+    }
+}
+
+/// Twice the area of the polygon, positive if it runs clockwise with y pointing down
+fn signed_area(ring: &[Pos]) -> isize {
+    (0..ring.len())
+        .map(|i| {
+            let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+            a.x as isize * b.y as isize - b.x as isize * a.y as isize
+        })
+        .sum()
+}
+
+impl Outline {
+    /// Number of corners over all rings, which is the number of sides
+    pub fn corners(&self) -> usize {
+        self.outer.len() + self.holes.iter().map(|hole| hole.len()).sum::<usize>()
+    }
+
+    /// One subpath per ring. The holes run the other way round, so they are cut out with either fill rule
+    pub fn to_svg_path(&self) -> String {
+        let mut path = String::new();
+        for ring in std::iter::once(&self.outer).chain(self.holes.iter()) {
+            for (i, pos) in ring.iter().enumerate() {
+                let command = if i == 0 { 'M' } else { 'L' };
+                write!(path, "{command}{} {}", pos.x, pos.y).unwrap();
+            }
+            path.push('Z');
+        }
+        path
+    }
+}
+
+impl Garden {
+    /// The whole garden as an SVG image, one path per region with a colour per plant
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\">\n",
+            self.width(), self.height()
+        );
+        for region in self.regions() {
+            let hue = (region.plant as u32 * 47) % 360;
+            writeln!(
+                svg,
+                "  <path d=\"{}\" fill=\"hsl({hue}, 60%, 60%)\" stroke=\"black\" stroke-width=\"0.05\"/>",
+                region.outline().to_svg_path()
+            ).unwrap();
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+#[cfg(test)]
+fn outlines(garden: &str) -> Vec<Outline> {
+    garden.parse::<Garden>().expect("Garden should parse").regions().iter().map(Region::outline).collect()
+}
+#[cfg(test)]
+fn ring(corners: &[(usize, usize)]) -> Vec<Pos> {
+    corners.iter().map(|c| Pos::from(*c)).collect()
+}
+
+#[test]
+fn test_square() {
+    let outlines = outlines("AA\nAA");
+    assert_eq!(outlines, vec![Outline { outer: ring(&[(0, 0), (2, 0), (2, 2), (0, 2)]), holes: vec![] }]);
+    assert_eq!(outlines[0].to_svg_path(), "M0 0L2 0L2 2L0 2Z");
+}
+
+#[test]
+fn test_hole() {
+    let outlines = outlines("OOO\nOXO\nOOO");
+    assert_eq!(outlines[0], Outline {
+        outer: ring(&[(0, 0), (3, 0), (3, 3), (0, 3)]),
+        holes: vec![ring(&[(1, 1), (1, 2), (2, 2), (2, 1)])],
+    });
+    assert_eq!(outlines[0].to_svg_path(), "M0 0L3 0L3 3L0 3ZM1 1L1 2L2 2L2 1Z");
+    assert_eq!(outlines[1].outer, ring(&[(1, 1), (2, 1), (2, 2), (1, 2)]));
+}
+
+#[test]
+fn test_diagonal_touch() {
+    // The fence around the B in the middle meets the outer fence in one corner, but is its own ring
+    let outlines = outlines("AAA\nABA\nAAB");
+    assert_eq!(outlines[0], Outline {
+        outer: ring(&[(0, 0), (3, 0), (3, 2), (2, 2), (2, 3), (0, 3)]),
+        holes: vec![ring(&[(1, 1), (1, 2), (2, 2), (2, 1)])],
+    });
+    assert_eq!(outlines[0].corners(), 10);
+}
+
+#[test]
+fn test_matches_side_counts() {
+    let mut rng = crate::utils::TestRng::new(0x2545f4914f6cdd1d);
+    for _ in 0..200 {
+        let garden: String = (0..7).map(|_| {
+            let row: String = (0..6).map(|_| {
+                char::from(b'A' + rng.below(3) as u8)
+            }).collect();
+            row + "\n"
+        }).collect();
+        let side_counts = super::to_side_counts(&super::parse(&garden));
+        for region in garden.parse::<Garden>().unwrap().regions() {
+            let outline = region.outline();
+            let sides: usize = region.cells.iter().map(|pos| side_counts.get(pos)).sum();
+            assert_eq!(outline.corners(), sides, "{garden}");
+            assert_eq!(outline.corners(), region.sides, "{garden}");
+            assert_eq!(outline.holes.len(), region.holes.len(), "{garden}");
+            // The shoelace area of the rings adds back up to the area of the region
+            let area = signed_area(&outline.outer) + outline.holes.iter().map(|hole| signed_area(hole)).sum::<isize>();
+            assert_eq!(area, 2 * region.area as isize, "{garden}");
+        }
+    }
+}
+
+#[test]
+fn test_svg() {
+    let svg = "AB".parse::<Garden>().unwrap().to_svg();
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 2 1\">\n"));
+    assert!(svg.contains("d=\"M0 0L1 0L1 1L0 1Z\""));
+    assert!(svg.contains("d=\"M1 0L2 0L2 1L1 1Z\""));
+    assert!(svg.ends_with("</svg>\n"));
+}
@@ -0,0 +1,324 @@
+use std::collections::VecDeque;
+use std::str::FromStr;
+use crate::day12::Pos;
+
+/// Plant of every plot, row by row
+pub struct Garden {
+    plants: Vec<char>,
+    width: usize,
+    height: usize,
+}
+impl FromStr for Garden {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<&str> = s.split_whitespace().collect();
+        let width = lines.first().ok_or(())?.chars().count();
+        if lines.iter().any(|line| line.chars().count() != width) {
+            return Err(());
+        }
+        let plants = lines.iter().flat_map(|line| line.chars()).collect();
+        Ok(Garden { plants, width, height: lines.len() })
+    }
+}
+
+/// Smallest rectangle containing a region, both corners included
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct BoundingBox {
+    pub min: Pos,
+    pub max: Pos,
+}
+
+/// A connected group of plots with the same plant
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Region {
+    /// Regions are numbered in the order their first plot appears, row by row
+    pub label: usize,
+    pub plant: char,
+    /// Every plot in the region, row by row
+    pub cells: Vec<Pos>,
+    pub area: usize,
+    /// Number of fence segments around the region, one per plot edge
+    pub perimeter: usize,
+    /// Number of straight fence sections, counted the puzzle's way: fences of plots that only
+    /// touch diagonally are separate
+    pub sides: usize,
+    pub bounding_box: BoundingBox,
+    /// Every hole in the region, as the labels of the regions filling it.
+    /// A hole is a group of plots, connected by their edges, that is surrounded by the region
+    pub holes: Vec<Vec<usize>>,
+}
+
+const EDGES: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const DIAGONALS: [(isize, isize); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+impl Garden {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    fn as_pos(&self, index: usize) -> Pos {
+        Pos { x: index % self.width, y: index / self.width }
+    }
+    fn index(&self, x: isize, y: isize) -> Option<usize> {
+        let in_range = x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height;
+        in_range.then(|| y as usize * self.width + x as usize)
+    }
+
+    /// Region label of every plot, row by row
+    pub fn labels(&self) -> Vec<usize> {
+        let mut labels: Vec<Option<usize>> = vec![None; self.plants.len()];
+        let mut next = 0;
+        for start in 0..self.plants.len() {
+            if labels[start].is_some() {
+                continue;
+            }
+            labels[start] = Some(next);
+            let mut queue = VecDeque::from([start]);
+            while let Some(index) = queue.pop_front() {
+                let Pos { x, y } = self.as_pos(index);
+                for (dx, dy) in EDGES {
+                    let Some(neighbor) = self.index(x as isize + dx, y as isize + dy) else {
+                        continue;
+                    };
+                    if labels[neighbor].is_none() && self.plants[neighbor] == self.plants[index] {
+                        labels[neighbor] = Some(next);
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+            next += 1;
+        }
+        labels.into_iter().map(|label| label.expect("Every plot is labelled")).collect()
+    }
+
+    pub fn regions(&self) -> Vec<Region> {
+        let labels = self.labels();
+        let count = labels.iter().max().map_or(0, |max| max + 1);
+        let mut cells: Vec<Vec<usize>> = vec![Vec::new(); count];
+        for (index, label) in labels.iter().enumerate() {
+            cells[*label].push(index);
+        }
+        cells.into_iter().enumerate()
+            .map(|(label, cells)| self.region(&labels, label, cells))
+            .collect()
+    }
+
+    fn region(&self, labels: &[usize], label: usize, cells: Vec<usize>) -> Region {
+        let inside = |x: isize, y: isize| self.index(x, y).is_some_and(|index| labels[index] == label);
+        let plant = self.plants[cells[0]];
+        let cells: Vec<Pos> = cells.into_iter().map(|index| self.as_pos(index)).collect();
+
+        let mut perimeter = 0;
+        let mut sides = 0;
+        for pos in cells.iter() {
+            let (x, y) = (pos.x as isize, pos.y as isize);
+            perimeter += EDGES.iter().filter(|(dx, dy)| !inside(x + dx, y + dy)).count();
+            // Every side ends in exactly one corner, so count those instead
+            sides += DIAGONALS.iter().filter(|(dx, dy)| {
+                let (beside, above, diagonal) = (inside(x + dx, y), inside(x, y + dy), inside(x + dx, y + dy));
+                (!beside && !above) || (beside && above && !diagonal)
+            }).count();
+        }
+
+        let bounding_box = BoundingBox {
+            min: Pos { x: cells.iter().map(|p| p.x).min().unwrap(), y: cells.iter().map(|p| p.y).min().unwrap() },
+            max: Pos { x: cells.iter().map(|p| p.x).max().unwrap(), y: cells.iter().map(|p| p.y).max().unwrap() },
+        };
+        let holes = self.holes(labels, &bounding_box, inside);
+
+        Region {
+            label,
+            plant,
+            area: cells.len(),
+            cells,
+            perimeter,
+            sides,
+            bounding_box,
+            holes,
+        }
+    }
+
+    /// Splits everything in the bounding box that isn't the region into groups connected by their
+    /// edges. The bounding box gets a border one plot wide all around, so the group touching that
+    /// border is the outside and every other group is a hole
+    fn holes(&self, labels: &[usize], bounding_box: &BoundingBox, inside: impl Fn(isize, isize) -> bool) -> Vec<Vec<usize>> {
+        let (left, top) = (bounding_box.min.x as isize - 1, bounding_box.min.y as isize - 1);
+        let width = bounding_box.max.x - bounding_box.min.x + 3;
+        let height = bounding_box.max.y - bounding_box.min.y + 3;
+        let local = |index: usize| (left + (index % width) as isize, top + (index / width) as isize);
+
+        let mut group: Vec<Option<usize>> = vec![None; width * height];
+        let mut holes: Vec<Vec<usize>> = Vec::new();
+        for start in 0..group.len() {
+            let (x, y) = local(start);
+            if group[start].is_some() || inside(x, y) {
+                continue;
+            }
+            // The first group starts in the corner of the border, and that is the outside
+            let id = holes.len();
+            let mut hole = Vec::new();
+            group[start] = Some(id);
+            let mut queue = VecDeque::from([start]);
+            while let Some(index) = queue.pop_front() {
+                let (x, y) = local(index);
+                if let Some(global) = self.index(x, y) {
+                    hole.push(labels[global]);
+                }
+                for (dx, dy) in EDGES {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if nx < left || ny < top || nx >= left + width as isize || ny >= top + height as isize {
+                        continue;
+                    }
+                    let neighbor = (ny - top) as usize * width + (nx - left) as usize;
+                    if group[neighbor].is_none() && !inside(nx, ny) {
+                        group[neighbor] = Some(id);
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+            hole.sort();
+            hole.dedup();
+            holes.push(hole);
+        }
+        holes.remove(0);
+        holes
+    }
+}
+
+impl Region {
+    pub fn to_json(&self) -> String {
+        let pos = |p: &Pos| format!("{{\"x\":{},\"y\":{}}}", p.x, p.y);
+        let cells: Vec<String> = self.cells.iter().map(|p| format!("[{},{}]", p.x, p.y)).collect();
+        let holes: Vec<String> = self.holes.iter()
+            .map(|hole| format!("[{}]", hole.iter().map(|l| l.to_string()).collect::<Vec<_>>().join(",")))
+            .collect();
+        format!(
+            "{{\"label\":{},\"plant\":\"{}\",\"area\":{},\"perimeter\":{},\"sides\":{},\"bounding_box\":{{\"min\":{},\"max\":{}}},\"cells\":[{}],\"holes\":[{}]}}",
+            self.label,
+            json_escape(self.plant),
+            self.area,
+            self.perimeter,
+            self.sides,
+            pos(&self.bounding_box.min),
+            pos(&self.bounding_box.max),
+            cells.join(","),
+            holes.join(","),
+        )
+    }
+}
+
+/// All regions as one JSON array
+pub fn to_json(regions: &[Region]) -> String {
+    format!("[{}]", regions.iter().map(Region::to_json).collect::<Vec<_>>().join(","))
+}
+
+fn json_escape(c: char) -> String {
+    match c {
+        '"' => String::from("\\\""),
+        '\\' => String::from("\\\\"),
+        c if c.is_control() => format!("\\u{:04x}", c as u32),
+        c => c.to_string(),
+    }
+}
+
+#[cfg(test)]
+const DEMO: &str = "\
+RRRRIICCFF
+RRRRIICCCF
+VVRRRCCFFF
+VVRCCCJFFF
+VVVVCJJCFE
+VVIVCCJJEE
+VVIIICJJEE
+MIIIIIJJEE
+MIIISIJEEE
+MMMISSJEEE
+";
+
+#[cfg(test)]
+fn label_garden(garden: &str) -> Vec<Region> {
+    garden.parse::<Garden>().expect("Garden should parse").regions()
+}
+
+#[test]
+fn test_demo() {
+    let regions = label_garden(DEMO);
+    assert_eq!(regions.len(), 11);
+    assert_eq!(regions.iter().map(|r| r.area * r.perimeter).sum::<usize>(), 1930);
+    assert_eq!(regions.iter().map(|r| r.area * r.sides).sum::<usize>(), 1206);
+    assert_eq!(regions.iter().map(|r| r.area).sum::<usize>(), 100);
+}
+
+#[test]
+fn test_region_fields() {
+    let regions = label_garden("AAAA\nBBCD\nBBCC\nEEEC");
+    let plants: Vec<char> = regions.iter().map(|r| r.plant).collect();
+    assert_eq!(plants, vec!['A', 'B', 'C', 'D', 'E']);
+    let c = &regions[2];
+    assert_eq!(c.label, 2);
+    assert_eq!(c.cells, vec![Pos { x: 2, y: 1 }, Pos { x: 2, y: 2 }, Pos { x: 3, y: 2 }, Pos { x: 3, y: 3 }]);
+    assert_eq!((c.area, c.perimeter, c.sides), (4, 10, 8));
+    assert_eq!(c.bounding_box, BoundingBox { min: Pos { x: 2, y: 1 }, max: Pos { x: 3, y: 3 } });
+    assert!(regions.iter().all(|r| r.holes.is_empty()));
+}
+
+#[test]
+fn test_holes() {
+    // Four separate holes
+    let regions = label_garden("OOOOO\nOXOXO\nOOOOO\nOXOXO\nOOOOO");
+    assert_eq!((regions[0].area, regions[0].perimeter, regions[0].sides), (21, 36, 20));
+    assert_eq!(regions[0].holes, vec![vec![1], vec![2], vec![3], vec![4]]);
+
+    // B regions only touching diagonally are separate holes, as their fences are separate
+    let regions = label_garden("AAAAAA\nAAABBA\nAAABBA\nABBAAA\nABBAAA\nAAAAAA");
+    assert_eq!(regions[0].sides, 12);
+    assert_eq!(regions[0].holes, vec![vec![1], vec![2]]);
+
+    // The B in the middle is still enclosed even though the outside reaches its corner
+    let regions = label_garden("AAA\nABA\nAAB");
+    assert_eq!(regions[0].sides, 10);
+    assert_eq!(regions[0].holes, vec![vec![1]]);
+    assert!(regions[2].holes.is_empty());
+}
+
+#[test]
+fn test_nested_regions() {
+    let regions = label_garden("AAAAA\nABBBA\nABCBA\nABBBA\nAAAAA");
+    assert_eq!(regions.len(), 3);
+    // The hole in A is filled by B, and C inside it
+    assert_eq!(regions[0].holes, vec![vec![1, 2]]);
+    assert_eq!(regions[1].holes, vec![vec![2]]);
+    assert!(regions[2].holes.is_empty());
+    assert_eq!(regions.iter().map(|r| r.sides).collect::<Vec<_>>(), vec![8, 8, 4]);
+}
+
+#[test]
+fn test_matches_per_cell_counts() {
+    let mut rng = crate::utils::TestRng::new(0x9e3779b97f4a7c15);
+    for _ in 0..200 {
+        let garden: String = (0..6).map(|_| {
+            let row: String = (0..7).map(|_| {
+                char::from(b'A' + rng.below(3) as u8)
+            }).collect();
+            row + "\n"
+        }).collect();
+        let plantmap = super::parse(&garden);
+        let regions = label_garden(&garden);
+        assert_eq!(regions.iter().map(|r| r.area * r.perimeter).sum::<usize>(), super::get_fence_cost(&plantmap), "{garden}");
+        assert_eq!(regions.iter().map(|r| r.area * r.sides).sum::<usize>(), super::get_side_cost(&plantmap), "{garden}");
+    }
+}
+
+#[test]
+fn test_json() {
+    let regions = label_garden("AA\nA\"");
+    assert_eq!(to_json(&regions), concat!(
+        "[{\"label\":0,\"plant\":\"A\",\"area\":3,\"perimeter\":8,\"sides\":6,",
+        "\"bounding_box\":{\"min\":{\"x\":0,\"y\":0},\"max\":{\"x\":1,\"y\":1}},\"cells\":[[0,0],[1,0],[0,1]],\"holes\":[]},",
+        "{\"label\":1,\"plant\":\"\\\"\",\"area\":1,\"perimeter\":4,\"sides\":4,",
+        "\"bounding_box\":{\"min\":{\"x\":1,\"y\":1},\"max\":{\"x\":1,\"y\":1}},\"cells\":[[1,1]],\"holes\":[]}]",
+    ));
+}
@@ -0,0 +1,263 @@
+/// Why a system has no solution in presses
+/// Ordered by how close the system came to being solvable
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy)]
+pub enum Unsolvable {
+    /// Some intermediate product doesn't fit in an i128. Coordinates below 2^62 never get there
+    TooLarge,
+    /// There are too many ways to press the buttons to try them all, so it isn't known
+    TooManyCombinations,
+    /// The buttons only move along one line and the target isn't on it
+    NoSolution,
+    /// The target can only be reached with fractional presses
+    NoIntegerSolution,
+    /// Reaching the target would take a negative number of presses of some button
+    NoNonNegativeSolution,
+    /// Reaching the target would take more presses of some button than it allows
+    TooManyPresses,
+    /// The target can be reached, but what the presses cost doesn't fit in a usize
+    CostTooLarge,
+}
+
+/// Most presses allowed for each button, None if there is no limit
+pub type Limits = (Option<i128>, Option<i128>);
+
+/// Cheapest non-negative integer (a, b) with `a * button_a + b * button_b == target`, where each
+/// press of a button costs the given amount and neither is pressed more often than its limit.
+/// Costs should not be negative.
+///
+/// If the buttons aren't collinear there is at most one solution, found with Cramer's rule.
+/// Otherwise the solutions lie on a line and the cheapest end of it is picked
+pub fn solve_2x2(button_a: (i128, i128), button_b: (i128, i128), target: (i128, i128), costs: (i128, i128), limits: Limits) -> Result<(i128, i128), Unsolvable> {
+    let (ax, ay) = button_a;
+    let (bx, by) = button_b;
+    let (tx, ty) = target;
+
+    let det = sub(mul(ax, by)?, mul(bx, ay)?)?;
+    if det == 0 {
+        return solve_collinear(button_a, button_b, target, costs, limits);
+    }
+    let a_numerator = sub(mul(tx, by)?, mul(ty, bx)?)?;
+    let b_numerator = sub(mul(ax, ty)?, mul(ay, tx)?)?;
+    if a_numerator % det != 0 || b_numerator % det != 0 {
+        return Err(Unsolvable::NoIntegerSolution);
+    }
+    let (a, b) = (a_numerator / det, b_numerator / det);
+    if a < 0 || b < 0 {
+        return Err(Unsolvable::NoNonNegativeSolution);
+    }
+    if limits.0.is_some_and(|limit| a > limit) || limits.1.is_some_and(|limit| b > limit) {
+        return Err(Unsolvable::TooManyPresses);
+    }
+    Ok((a, b))
+}
+
+fn solve_collinear(button_a: (i128, i128), button_b: (i128, i128), target: (i128, i128), costs: (i128, i128), limits: Limits) -> Result<(i128, i128), Unsolvable> {
+    // Any button that moves at all gives the direction of the line
+    let direction = if button_a != (0, 0) { button_a } else { button_b };
+    if direction == (0, 0) {
+        return if target == (0, 0) { Ok((0, 0)) } else { Err(Unsolvable::NoSolution) };
+    }
+    // Shortest step along the line, which keeps the cross product small
+    let (g, _, _) = extended_gcd(direction.0, direction.1);
+    let direction = (direction.0 / g, direction.1 / g);
+    if sub(mul(direction.0, target.1)?, mul(direction.1, target.0)?)? != 0 {
+        return Err(Unsolvable::NoSolution);
+    }
+    // On the line, one axis the buttons move along is enough
+    if button_a.0 != 0 || button_b.0 != 0 {
+        solve_1d(button_a.0, button_b.0, target.0, costs, limits)
+    } else {
+        solve_1d(button_a.1, button_b.1, target.1, costs, limits)
+    }
+}
+
+/// Cheapest non-negative integer (a, b) within the limits with `a * p + b * q == r`
+fn solve_1d(p: i128, q: i128, r: i128, (cost_a, cost_b): (i128, i128), limits: Limits) -> Result<(i128, i128), Unsolvable> {
+    match (p, q) {
+        (0, 0) => return if r == 0 { Ok((0, 0)) } else { Err(Unsolvable::NoSolution) },
+        (_, 0) | (0, _) => {
+            let (step, limit) = if q == 0 { (p, limits.0) } else { (q, limits.1) };
+            if r % step != 0 {
+                return Err(Unsolvable::NoIntegerSolution);
+            }
+            if r / step < 0 {
+                return Err(Unsolvable::NoNonNegativeSolution);
+            }
+            if limit.is_some_and(|limit| r / step > limit) {
+                return Err(Unsolvable::TooManyPresses);
+            }
+            return Ok(if q == 0 { (r / step, 0) } else { (0, r / step) });
+        }
+        _ => {}
+    }
+
+    let (g, x, y) = extended_gcd(p, q);
+    if r % g != 0 {
+        return Err(Unsolvable::NoIntegerSolution);
+    }
+    // Every solution is (a0 + t * a_step, b0 + t * b_step) for some integer t
+    let (a0, b0) = (mul(x, r / g)?, mul(y, r / g)?);
+    let (a_step, b_step) = (q / g, sub(0, p / g)?);
+
+    // Both counts have to stay non-negative, which bounds t from one side each
+    let mut low: Option<i128> = None;
+    let mut high: Option<i128> = None;
+    for (start, step) in [(a0, a_step), (b0, b_step)] {
+        // start + t * step >= 0
+        if step > 0 {
+            let bound = div_ceil(sub(0, start)?, step);
+            low = Some(low.map_or(bound, |low| low.max(bound)));
+        } else {
+            let bound = div_floor(start, -step);
+            high = Some(high.map_or(bound, |high| high.min(bound)));
+        }
+    }
+    if let (Some(low), Some(high)) = (low, high) {
+        if low > high {
+            return Err(Unsolvable::NoNonNegativeSolution);
+        }
+    }
+    // And the limits bound it from the other side
+    for (start, step, limit) in [(a0, a_step, limits.0), (b0, b_step, limits.1)] {
+        let Some(limit) = limit else { continue };
+        // start + t * step <= limit
+        if step > 0 {
+            let bound = div_floor(sub(limit, start)?, step);
+            high = Some(high.map_or(bound, |high| high.min(bound)));
+        } else {
+            let bound = div_ceil(sub(start, limit)?, -step);
+            low = Some(low.map_or(bound, |low| low.max(bound)));
+        }
+    }
+    if let (Some(low), Some(high)) = (low, high) {
+        if low > high {
+            return Err(Unsolvable::TooManyPresses);
+        }
+    }
+
+    // The cost changes linearly with t, so the cheapest solution is at one end.
+    // With non-negative costs the cost can't fall forever, so the end it falls towards is bounded
+    let slope = add(mul(cost_a, a_step)?, mul(cost_b, b_step)?)?;
+    let t = match (slope > 0, low, high) {
+        (true, Some(low), _) | (false, Some(low), None) => low,
+        (_, _, Some(high)) => high,
+        (_, None, None) => unreachable!("Each count bounds t from one side"),
+    };
+    Ok((add(a0, mul(t, a_step)?)?, add(b0, mul(t, b_step)?)?))
+}
+
+fn add(a: i128, b: i128) -> Result<i128, Unsolvable> {
+    a.checked_add(b).ok_or(Unsolvable::TooLarge)
+}
+fn sub(a: i128, b: i128) -> Result<i128, Unsolvable> {
+    a.checked_sub(b).ok_or(Unsolvable::TooLarge)
+}
+fn mul(a: i128, b: i128) -> Result<i128, Unsolvable> {
+    a.checked_mul(b).ok_or(Unsolvable::TooLarge)
+}
+
+/// (g, x, y) with `p * x + q * y == g` and g positive
+fn extended_gcd(p: i128, q: i128) -> (i128, i128, i128) {
+    if q == 0 {
+        return (p.abs(), p.signum(), 0);
+    }
+    let (g, x, y) = extended_gcd(q, p.rem_euclid(q));
+    (g, y, x - p.div_euclid(q) * y)
+}
+
+fn div_floor(a: i128, b: i128) -> i128 {
+    let quotient = a / b;
+    if a % b != 0 && (a < 0) != (b < 0) { quotient - 1 } else { quotient }
+}
+fn div_ceil(a: i128, b: i128) -> i128 {
+    let quotient = a / b;
+    if a % b != 0 && (a < 0) == (b < 0) { quotient + 1 } else { quotient }
+}
+
+#[cfg(test)]
+const UNLIMITED: Limits = (None, None);
+
+#[test]
+fn test_demo_machines() {
+    assert_eq!(solve_2x2((94, 34), (22, 67), (8400, 5400), (3, 1), UNLIMITED), Ok((80, 40)));
+    assert_eq!(solve_2x2((26, 66), (67, 21), (12748, 12176), (3, 1), UNLIMITED), Err(Unsolvable::NoIntegerSolution));
+    assert_eq!(solve_2x2((17, 86), (84, 37), (7870, 6450), (3, 1), UNLIMITED), Ok((38, 86)));
+    assert_eq!(solve_2x2((69, 23), (27, 71), (18641, 10279), (3, 1), UNLIMITED), Err(Unsolvable::NoIntegerSolution));
+}
+
+#[test]
+fn test_signs() {
+    // Reachable, but only by pressing A a negative number of times
+    assert_eq!(solve_2x2((1, 0), (0, 1), (-2, 3), (3, 1), UNLIMITED), Err(Unsolvable::NoNonNegativeSolution));
+    // Buttons that move backwards are fine
+    assert_eq!(solve_2x2((-1, 2), (3, -1), (1, 3), (3, 1), UNLIMITED), Ok((2, 1)));
+    assert_eq!(solve_2x2((-1, 2), (3, -1), (-1, -3), (3, 1), UNLIMITED), Err(Unsolvable::NoNonNegativeSolution));
+}
+
+#[test]
+fn test_collinear() {
+    // B moves twice as far as A for a third of the price, so only use A for what's left over
+    assert_eq!(solve_2x2((1, 1), (2, 2), (7, 7), (3, 1), UNLIMITED), Ok((1, 3)));
+    // If A is cheaper per distance it's the other way round
+    assert_eq!(solve_2x2((1, 1), (2, 2), (7, 7), (1, 3), UNLIMITED), Ok((7, 0)));
+    assert_eq!(solve_2x2((3, 6), (5, 10), (19, 38), (1, 1), UNLIMITED), Ok((3, 2)));
+    assert_eq!(solve_2x2((2, 2), (4, 4), (7, 7), (3, 1), UNLIMITED), Err(Unsolvable::NoIntegerSolution));
+    assert_eq!(solve_2x2((1, 1), (2, 2), (3, 4), (3, 1), UNLIMITED), Err(Unsolvable::NoSolution));
+    assert_eq!(solve_2x2((3, 0), (5, 0), (7, 0), (3, 1), UNLIMITED), Err(Unsolvable::NoNonNegativeSolution));
+    // Moving in opposite directions there are endless solutions, the cheapest presses least
+    assert_eq!(solve_2x2((3, 3), (-2, -2), (1, 1), (1, 1), UNLIMITED), Ok((1, 1)));
+    assert_eq!(solve_2x2((0, 2), (0, -3), (0, -1), (1, 1), UNLIMITED), Ok((1, 1)));
+    // Buttons that don't move at all
+    assert_eq!(solve_2x2((0, 0), (0, 4), (0, 8), (3, 1), UNLIMITED), Ok((0, 2)));
+    assert_eq!(solve_2x2((0, 0), (0, 0), (0, 0), (3, 1), UNLIMITED), Ok((0, 0)));
+    assert_eq!(solve_2x2((0, 0), (0, 0), (1, 0), (3, 1), UNLIMITED), Err(Unsolvable::NoSolution));
+}
+
+#[test]
+fn test_collinear_brute_force() {
+    for (p, q) in [(3, 5), (4, 6), (-3, 5), (5, -2), (7, 7)] {
+        for r in -20..40 {
+            for costs in [(3, 1), (1, 3), (2, 2)] {
+                let expected = (0..=40i128).flat_map(|a| (0..=40i128).map(move |b| (a, b)))
+                    .filter(|(a, b)| a * p + b * q == r)
+                    .min_by_key(|(a, b)| (a * costs.0 + b * costs.1, *a));
+                let found = solve_2x2((p, 2 * p), (q, 2 * q), (r, 2 * r), costs, UNLIMITED);
+                match expected {
+                    Some((a, b)) => {
+                        let (fa, fb) = found.expect("Brute force found a solution");
+                        assert_eq!(fa * p + fb * q, r);
+                        assert_eq!(fa * costs.0 + fb * costs.1, a * costs.0 + b * costs.1, "{p} {q} {r} {costs:?}");
+                    }
+                    None => assert!(found.is_err(), "{p} {q} {r} {costs:?} gave {found:?}"),
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_large_values() {
+    // Products of the coordinates are far beyond usize, but fine in i128
+    let offset = 10_000_000_000_000i128;
+    assert_eq!(solve_2x2((94, 34), (22, 67), (8400 + offset, 5400 + offset), (3, 1), UNLIMITED), Err(Unsolvable::NoIntegerSolution));
+    assert_eq!(solve_2x2((26, 66), (67, 21), (12748 + offset, 12176 + offset), (3, 1), UNLIMITED), Ok((118679050709, 103199174542)));
+    let huge = i64::MAX as i128;
+    assert_eq!(solve_2x2((huge, 1), (1, huge), (huge + 1, huge + 1), (3, 1), UNLIMITED), Ok((1, 1)));
+    // Beyond that the products stop fitting, which is reported rather than wrapping around
+    let huge = u64::MAX as i128;
+    assert_eq!(solve_2x2((huge, 1), (1, huge), (huge + 1, huge + 1), (3, 1), UNLIMITED), Err(Unsolvable::TooLarge));
+    assert_eq!(solve_2x2((huge, huge), (1, 1), (huge, huge), (3, 1), UNLIMITED), Ok((1, 0)));
+}
+
+#[test]
+fn test_limits() {
+    assert_eq!(solve_2x2((94, 34), (22, 67), (8400, 5400), (3, 1), (Some(100), Some(100))), Ok((80, 40)));
+    assert_eq!(solve_2x2((94, 34), (22, 67), (8400, 5400), (3, 1), (Some(79), None)), Err(Unsolvable::TooManyPresses));
+    // The cheapest way would use B 3 times, but it may only be pressed twice
+    assert_eq!(solve_2x2((1, 1), (2, 2), (7, 7), (3, 1), (None, Some(2))), Ok((3, 2)));
+    assert_eq!(solve_2x2((1, 1), (2, 2), (7, 7), (3, 1), (Some(2), Some(2))), Err(Unsolvable::TooManyPresses));
+    assert_eq!(solve_2x2((0, 0), (0, 4), (0, 8), (3, 1), (None, Some(1))), Err(Unsolvable::TooManyPresses));
+    // Endless solutions only get as far as the limits allow
+    assert_eq!(solve_2x2((3, 3), (-2, -2), (1, 1), (1, 1), (Some(0), None)), Err(Unsolvable::TooManyPresses));
+    assert_eq!(solve_2x2((3, 3), (-2, -2), (1, 1), (1, 5), (Some(10), Some(10))), Ok((1, 1)));
+}
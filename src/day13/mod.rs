@@ -1,26 +1,54 @@
 use crate::utils::read_input_file;
+use crate::day13::linear::{solve_2x2, Unsolvable};
 use nom::{IResult, Parser};
+use std::str::FromStr;
+
+pub mod linear;
 
 const DAY: &str = "day13";
+/// What pressing A and B costs in the puzzle
+const COST_A: usize = 3;
+const COST_B: usize = 1;
+/// Part 1 says no button needs more than 100 presses
+const PART1_PRESS_LIMIT: usize = 100;
+/// Part 2 moves every prize this far along both axes
+const PART2_GOAL_OFFSET: usize = 10000000000000;
+/// Most combinations of presses of the extra buttons that [ClawGame::solve] will try
+const SEARCH_LIMIT: usize = 10_000_000;
 
-#[derive(Debug)]
-struct Vector2D {
-    x: usize, 
-    y: usize,
+#[derive(Debug, Clone, Copy)]
+pub struct Vector2D {
+    pub x: usize,
+    pub y: usize,
+}
+#[derive(Debug, Clone)]
+pub struct Button {
+    pub movement: Vector2D,
+    pub cost: usize,
+    pub max_presses: Option<usize>,
+}
+impl Button {
+    pub fn new(x: usize, y: usize, cost: usize) -> Button {
+        Button { movement: Vector2D { x, y }, cost, max_presses: None }
+    }
+    pub fn with_max_presses(self, max_presses: usize) -> Button {
+        Button { max_presses: Some(max_presses), ..self }
+    }
 }
-#[derive(Debug)]
-struct ClawGame {
-    goal: Vector2D,
-    button_a: Vector2D,
-    button_b: Vector2D,
+#[derive(Debug, Clone)]
+pub struct ClawGame {
+    pub goal: Vector2D,
+    pub buttons: Vec<Button>,
 }
-struct Solution {
-    a_presses: usize,
-    b_presses: usize,
+#[derive(Debug, Eq, PartialEq)]
+pub struct Solution {
+    /// How often each button is pressed, in the same order as the buttons
+    pub presses: Vec<usize>,
+    pub cost: usize,
 }
 impl Solution {
     fn get_cost(&self) -> usize {
-        return self.a_presses*3 + self.b_presses;
+        self.cost
     }
 }
 
@@ -39,7 +67,10 @@ fn parse_claw_game(str: &str) -> IResult<&str, ClawGame> {
 
     let newline = nom::character::complete::newline;
     return (line1, newline, line2, newline, line3)
-        .map(|(button_a,_, button_b,_, goal)| ClawGame {goal, button_a, button_b})
+        .map(|(button_a,_, button_b,_, goal)| ClawGame {goal, buttons: vec![
+            Button { movement: button_a, cost: COST_A, max_presses: None },
+            Button { movement: button_b, cost: COST_B, max_presses: None },
+        ]})
         .parse(str);
 }
 fn parse_claw_games(str: &str) -> IResult<&str, Vec<ClawGame>> {
@@ -49,64 +80,340 @@ fn parse_claw_games(str: &str) -> IResult<&str, Vec<ClawGame>> {
 }
 
 impl ClawGame {
-    fn solve(&self) -> Option<Solution> {
-        let Vector2D { x: x1, y: y1 } = self.button_a;
-        let Vector2D { x: x2, y: y2 } = self.button_b;
-        let Vector2D { x: xt, y: yt } = self.goal;
-        
-        // k = (y2*xt - yt*x2) / (x1*y2 - x2*y1)
-        let a_presses = (y2*xt).abs_diff(yt*x2) / (x1*y2).abs_diff(x2*y1);
-        
-        let moved_x = x1 * a_presses;
-        if moved_x > xt {
-            return None;
-        }
-        let b_presses = (xt - moved_x) / x2;
-        
-        if a_presses * x1 + b_presses * x2 == xt {
-            if a_presses * y1 + b_presses * y2 == yt {
-                return Some(Solution { a_presses, b_presses });
+    pub fn new(goal: Vector2D, buttons: Vec<Button>) -> ClawGame {
+        ClawGame { goal, buttons }
+    }
+    /// Limits every button to at most this many presses, on top of any limit it already has
+    pub fn with_max_presses(self, max_presses: usize) -> ClawGame {
+        let buttons = self.buttons.into_iter()
+            .map(|button| {
+                let max_presses = button.max_presses.map_or(max_presses, |max| max.min(max_presses));
+                button.with_max_presses(max_presses)
+            })
+            .collect();
+        ClawGame { buttons, ..self }
+    }
+
+    /// Cheapest way to move the claw exactly onto the prize.
+    ///
+    /// Two buttons are solved directly. With more, every combination of presses of all but the
+    /// last two buttons is tried, and the last two are solved directly for what's left. Buttons
+    /// only move forward, so the presses of each button are bounded by the distance to the goal.
+    /// If no combination works, the error is the one that came closest.
+    ///
+    /// Far away prizes, like those of part 2, allow far too many combinations. Unless press limits
+    /// keep them below [SEARCH_LIMIT], such games fail with [Unsolvable::TooManyCombinations]
+    pub fn solve(&self) -> Result<Solution, Unsolvable> {
+        let goal = (self.goal.x as i128, self.goal.y as i128);
+        let presses = match self.buttons.as_slice() {
+            [] if goal == (0, 0) => Ok(vec![]),
+            [] => Err(Unsolvable::NoSolution),
+            // A button that doesn't move can stand in for the missing second one
+            [button] => solve_pair(button, &Button::new(0, 0, 0), goal).map(|(a, _)| vec![a]),
+            _ => {
+                let extra = &self.buttons[..self.buttons.len() - 2];
+                let combinations = extra.iter()
+                    .try_fold(1usize, |total, button| total.checked_mul(most_presses(button, goal).checked_add(1)?));
+                if combinations.is_none_or(|combinations| combinations > SEARCH_LIMIT) {
+                    return Err(Unsolvable::TooManyCombinations);
+                }
+                let mut presses = vec![0; self.buttons.len()];
+                let mut best = None;
+                self.search(0, goal, &mut presses, &mut best);
+                best.expect("The search always finds a solution or an error")
+            }
+        }?;
+        let cost = self.cost(&presses).ok_or(Unsolvable::CostTooLarge)?;
+        Ok(Solution { presses, cost })
+    }
+
+    /// What pressing the buttons this often costs, or None if that doesn't fit in a usize
+    fn cost(&self, presses: &[usize]) -> Option<usize> {
+        presses.iter().zip(self.buttons.iter())
+            .try_fold(0usize, |total, (presses, button)| total.checked_add(presses.checked_mul(button.cost)?))
+    }
+
+    fn search(&self, index: usize, remaining: (i128, i128), presses: &mut Vec<usize>, best: &mut Option<Result<Vec<usize>, Unsolvable>>) {
+        if index == self.buttons.len() - 2 {
+            let found = solve_pair(&self.buttons[index], &self.buttons[index + 1], remaining).and_then(|(a, b)| {
+                presses[index] = a;
+                presses[index + 1] = b;
+                // Only kept solutions have their cost compared, so they all have to have one
+                self.cost(presses).ok_or(Unsolvable::CostTooLarge).map(|_| presses.clone())
+            });
+            *best = match (best.take(), found) {
+                (Some(Ok(old)), Ok(new)) => Some(Ok(if self.cost(&new) < self.cost(&old) { new } else { old })),
+                (Some(Ok(old)), Err(_)) | (Some(Err(_)), Ok(old)) => Some(Ok(old)),
+                (Some(Err(old)), Err(new)) => Some(Err(old.max(new))),
+                (None, found) => Some(found),
             };
-        };
-        return None;
+            return;
+        }
+
+        let button = &self.buttons[index];
+        let Vector2D { x, y } = button.movement;
+        for count in 0..=most_presses(button, remaining) {
+            presses[index] = count;
+            let moved = (count as i128 * x as i128, count as i128 * y as i128);
+            self.search(index + 1, (remaining.0 - moved.0, remaining.1 - moved.1), presses, best);
+        }
+        presses[index] = 0;
+    }
+}
+
+/// Every claw machine in the arcade
+#[derive(Debug, Clone)]
+pub struct ClawGames {
+    games: Vec<ClawGame>,
+}
+impl FromStr for ClawGames {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_claw_games(s.trim()) {
+            Ok(("", games)) => Ok(ClawGames { games }),
+            _ => Err(()),
+        }
+    }
+}
+impl ClawGames {
+    pub fn games(&self) -> &[ClawGame] {
+        &self.games
+    }
+    /// Every prize moved by `offset` along both axes, or None if a prize ends up beyond usize
+    pub fn with_goal_offset(self, offset: usize) -> Option<ClawGames> {
+        let games = self.games.into_iter()
+            .map(|game| {
+                let goal = Vector2D { x: game.goal.x.checked_add(offset)?, y: game.goal.y.checked_add(offset)? };
+                Some(ClawGame { goal, ..game })
+            })
+            .collect::<Option<_>>()?;
+        Some(ClawGames { games })
+    }
+    pub fn with_max_presses(self, max_presses: usize) -> ClawGames {
+        ClawGames { games: self.games.into_iter().map(|game| game.with_max_presses(max_presses)).collect() }
+    }
+
+    /// The solution for every machine, in order
+    pub fn solve_all(&self) -> Vec<Result<Solution, Unsolvable>> {
+        self.games.iter().map(ClawGame::solve).collect()
+    }
+    /// Tokens needed to win every prize that can be won
+    pub fn total_cost(&self) -> usize {
+        self.solve_all().iter().flatten().map(Solution::get_cost).sum()
+    }
+}
+
+/// How often the button can be pressed before it overshoots `remaining` or its own limit
+fn most_presses(button: &Button, remaining: (i128, i128)) -> usize {
+    let Vector2D { x, y } = button.movement;
+    [(x, remaining.0), (y, remaining.1)].into_iter()
+        .filter(|(step, _)| *step > 0)
+        .map(|(step, remaining)| (remaining.max(0) / step as i128) as usize)
+        .chain(button.max_presses)
+        .min()
+        .unwrap_or(0)
+}
+
+fn solve_pair(a: &Button, b: &Button, goal: (i128, i128)) -> Result<(usize, usize), Unsolvable> {
+    let movement = |button: &Button| (button.movement.x as i128, button.movement.y as i128);
+    let limit = |button: &Button| button.max_presses.map(|max| max as i128);
+    let (a_presses, b_presses) = solve_2x2(
+        movement(a),
+        movement(b),
+        goal,
+        (a.cost as i128, b.cost as i128),
+        (limit(a), limit(b)),
+    )?;
+    // Presses never exceed the goal, which came from a usize
+    Ok((a_presses as usize, b_presses as usize))
+}
+
+#[cfg(test)]
+const DEMO: &str = "\
+Button A: X+94, Y+34
+Button B: X+22, Y+67
+Prize: X=8400, Y=5400
+
+Button A: X+26, Y+66
+Button B: X+67, Y+21
+Prize: X=12748, Y=12176
+
+Button A: X+17, Y+86
+Button B: X+84, Y+37
+Prize: X=7870, Y=6450
+
+Button A: X+69, Y+23
+Button B: X+27, Y+71
+Prize: X=18641, Y=10279";
+
+#[test]
+fn test_solve() {
+    let games = parse_claw_games(DEMO).unwrap().1;
+    let solutions: Vec<Result<Solution, Unsolvable>> = games.iter().map(ClawGame::solve).collect();
+    assert_eq!(solutions, vec![
+        Ok(Solution { presses: vec![80, 40], cost: 280 }),
+        Err(Unsolvable::NoIntegerSolution),
+        Ok(Solution { presses: vec![38, 86], cost: 200 }),
+        Err(Unsolvable::NoIntegerSolution),
+    ]);
+
+    // Collinear buttons used to divide by zero
+    let game = ClawGame::new(Vector2D { x: 7, y: 7 }, vec![Button::new(1, 1, 3), Button::new(2, 2, 1)]);
+    assert_eq!(game.solve(), Ok(Solution { presses: vec![1, 3], cost: 6 }));
+    // Only reachable by pressing A a negative number of times, which the old version couldn't tell
+    let game = ClawGame::new(Vector2D { x: 1, y: 8 }, vec![Button::new(2, 1, 3), Button::new(1, 3, 1)]);
+    assert_eq!(game.solve(), Err(Unsolvable::NoNonNegativeSolution));
+}
+
+#[test]
+fn test_press_limits() {
+    let games = parse_claw_games(DEMO).unwrap().1;
+    let game = games[0].clone().with_max_presses(PART1_PRESS_LIMIT);
+    assert_eq!(game.solve().map(|s| s.cost), Ok(280));
+    let game = games[0].clone().with_max_presses(50);
+    assert_eq!(game.solve(), Err(Unsolvable::TooManyPresses));
+
+    let game = ClawGame::new(Vector2D { x: 7, y: 7 }, vec![Button::new(1, 1, 3), Button::new(2, 2, 1).with_max_presses(2)]);
+    assert_eq!(game.solve(), Ok(Solution { presses: vec![3, 2], cost: 11 }));
+}
+
+#[test]
+fn test_more_buttons() {
+    // A single button either gets there or doesn't
+    let game = ClawGame::new(Vector2D { x: 6, y: 9 }, vec![Button::new(2, 3, 5)]);
+    assert_eq!(game.solve(), Ok(Solution { presses: vec![3], cost: 15 }));
+    let game = ClawGame::new(Vector2D { x: 6, y: 8 }, vec![Button::new(2, 3, 5)]);
+    assert_eq!(game.solve(), Err(Unsolvable::NoSolution));
+    assert_eq!(ClawGame::new(Vector2D { x: 0, y: 0 }, vec![]).solve(), Ok(Solution { presses: vec![], cost: 0 }));
+
+    // A third, expensive button that moves diagonally is only worth it when it saves enough presses
+    let buttons = vec![Button::new(1, 0, 1), Button::new(0, 1, 1), Button::new(1, 1, 1)];
+    let game = ClawGame::new(Vector2D { x: 5, y: 3 }, buttons.clone());
+    assert_eq!(game.solve(), Ok(Solution { presses: vec![2, 0, 3], cost: 5 }));
+    let mut expensive = buttons.clone();
+    expensive[2].cost = 3;
+    let game = ClawGame::new(Vector2D { x: 5, y: 3 }, expensive);
+    assert_eq!(game.solve(), Ok(Solution { presses: vec![5, 3, 0], cost: 8 }));
+    let mut capped = buttons;
+    capped[2] = capped[2].clone().with_max_presses(1);
+    let game = ClawGame::new(Vector2D { x: 5, y: 3 }, capped);
+    assert_eq!(game.solve(), Ok(Solution { presses: vec![4, 2, 1], cost: 7 }));
+
+    // Brute force over every combination of three buttons
+    let buttons = vec![Button::new(3, 1, 3), Button::new(1, 2, 1), Button::new(2, 5, 2)];
+    for x in 0..25 {
+        for y in 0..25 {
+            let expected = (0..=25).flat_map(|a| (0..=25).flat_map(move |b| (0..=25).map(move |c| (a, b, c))))
+                .filter(|(a, b, c)| 3 * a + b + 2 * c == x && a + 2 * b + 5 * c == y)
+                .map(|(a, b, c)| 3 * a + b + 2 * c)
+                .min();
+            let game = ClawGame::new(Vector2D { x, y }, buttons.clone());
+            assert_eq!(game.solve().ok().map(|s| s.cost), expected, "{x} {y}");
+        }
     }
 }
+
 #[test]
 fn test_part1() {
     let demo = read_input_file(DAY, "demo.txt");
-    let games = parse_claw_games(&demo).unwrap().1;
-    let mut total = 0;
-    for game in games {
-        if let Some(solution) = game.solve() {
-            total += solution.get_cost();
-        }
-    }
-    assert_eq!(total, 480);
+    let games: ClawGames = demo.parse().expect("Input should parse");
+    assert_eq!(games.with_max_presses(PART1_PRESS_LIMIT).total_cost(), 480);
+}
+
+#[test]
+fn test_part2() {
+    let games: ClawGames = DEMO.parse().expect("Demo should parse");
+    let games = games.with_goal_offset(PART2_GOAL_OFFSET).expect("Goals should fit in a usize");
+    let solutions = games.solve_all();
+    assert_eq!(solutions, vec![
+        Err(Unsolvable::NoIntegerSolution),
+        Ok(Solution { presses: vec![118679050709, 103199174542], cost: 459236326669 }),
+        Err(Unsolvable::NoIntegerSolution),
+        Ok(Solution { presses: vec![102851800151, 107526881786], cost: 416082282239 }),
+    ]);
+    assert_eq!(games.total_cost(), 875318608908);
+}
+
+#[test]
+fn test_more_buttons_at_part2_scale() {
+    let games: ClawGames = DEMO.parse().expect("Demo should parse");
+    let game = games.with_goal_offset(PART2_GOAL_OFFSET).expect("Goals should fit in a usize").games()[1].clone();
+    let with_diagonal = |diagonal: Button| {
+        let mut buttons = vec![diagonal];
+        buttons.extend(game.buttons.iter().cloned());
+        ClawGame::new(game.goal, buttons)
+    };
+    // Trying every press count of the extra button would take about 10^13 tries
+    assert_eq!(with_diagonal(Button::new(1, 1, 1)).solve(), Err(Unsolvable::TooManyCombinations));
+
+    // A press limit brings it back within reach, the two original buttons take care of the rest
+    let limited = with_diagonal(Button::new(1, 1, 1).with_max_presses(1000));
+    let expected = (0..=1000)
+        .filter_map(|c| {
+            let goal = Vector2D { x: game.goal.x - c, y: game.goal.y - c };
+            ClawGame::new(goal, game.buttons.clone()).solve().ok().map(|s| s.cost + c)
+        })
+        .min();
+    assert!(expected.is_some());
+    assert_eq!(limited.solve().ok().map(|s| s.cost), expected);
+}
+
+#[test]
+fn test_near_usize_limits() {
+    let games: ClawGames = DEMO.parse().expect("Demo should parse");
+    assert!(games.clone().with_goal_offset(usize::MAX - 8400).is_none());
+
+    // Prizes right at the end of usize, where x1 * y2 style products need far more than 64 bits
+    let (a, b) = (usize::MAX / 200, usize::MAX / 300);
+    let goal = Vector2D { x: 94 * a + 22 * b, y: 34 * a + 67 * b };
+    let game = ClawGame::new(goal, vec![Button::new(94, 34, COST_A), Button::new(22, 67, COST_B)]);
+    assert_eq!(game.solve().map(|s| s.presses), Ok(vec![a, b]));
+    // One step further along is no longer reachable
+    let game = ClawGame::new(Vector2D { x: goal.x + 1, y: goal.y }, game.buttons);
+    assert_eq!(game.solve(), Err(Unsolvable::NoIntegerSolution));
+
+    // Button moves near the limit too still fit in i128 up to 2^62
+    let big = 1usize << 62;
+    let game = ClawGame::new(Vector2D { x: big + 1, y: big + 2 }, vec![Button::new(big, 1, 3), Button::new(1, big, 1)]);
+    assert!(game.solve().is_err());
+    let game = ClawGame::new(Vector2D { x: big + 1, y: 1 + big }, vec![Button::new(big, 1, 3), Button::new(1, big, 1)]);
+    assert_eq!(game.solve().map(|s| s.presses), Ok(vec![1, 1]));
+    // And past that overflow is reported instead of wrapping around
+    let game = ClawGame::new(Vector2D { x: usize::MAX, y: usize::MAX }, vec![Button::new(usize::MAX, 1, 3), Button::new(1, usize::MAX, 1)]);
+    assert_eq!(game.solve(), Err(Unsolvable::TooLarge));
+}
+
+#[test]
+fn test_cost_overflow() {
+    // The presses fit, but what they cost doesn't
+    let game = ClawGame::new(Vector2D { x: 2, y: 3 }, vec![Button::new(1, 0, usize::MAX), Button::new(0, 1, 1)]);
+    assert_eq!(game.solve(), Err(Unsolvable::CostTooLarge));
+    let game = ClawGame::new(Vector2D { x: 1, y: 3 }, vec![Button::new(1, 0, usize::MAX), Button::new(0, 1, 1)]);
+    assert_eq!(game.solve(), Err(Unsolvable::CostTooLarge));
+    let game = ClawGame::new(Vector2D { x: 1, y: 0 }, vec![Button::new(1, 0, usize::MAX), Button::new(0, 1, 1)]);
+    assert_eq!(game.solve().map(|s| s.cost), Ok(usize::MAX));
+
+    // With more buttons, a combination that overflows loses to one that doesn't
+    let buttons = vec![Button::new(1, 1, usize::MAX), Button::new(1, 0, 1), Button::new(0, 1, 1)];
+    let game = ClawGame::new(Vector2D { x: 2, y: 2 }, buttons.clone());
+    assert_eq!(game.solve(), Ok(Solution { presses: vec![0, 2, 2], cost: 4 }));
+    // Pressing each once is the only way left, and reaching the prize beats every other error
+    // even if it overflows
+    let game = ClawGame::new(Vector2D { x: 2, y: 2 }, buttons.clone()).with_max_presses(1);
+    assert_eq!(game.solve(), Err(Unsolvable::CostTooLarge));
+    let game = ClawGame::new(Vector2D { x: 3, y: 2 }, buttons).with_max_presses(2);
+    assert_eq!(game.solve(), Err(Unsolvable::CostTooLarge));
 }
 
 
 pub fn part1() -> usize {
     let full = read_input_file(DAY, "full.txt");
-    let games = parse_claw_games(&full).unwrap().1;
-    let mut total = 0;
-    for game in games {
-        if let Some(solution) = game.solve() {
-            total += solution.get_cost();
-        }
-    }
-    return total;
+    let games: ClawGames = full.parse().expect("Input should parse");
+    games.with_max_presses(PART1_PRESS_LIMIT).total_cost()
 }
 pub fn part2() -> usize {
-    const OFFSET: usize = 10000000000000;
-    
     let full = read_input_file(DAY, "full.txt");
-    let mut games = parse_claw_games(&full).unwrap().1;
-    let mut total = 0;
-    for mut game in games {
-        game.goal = Vector2D{ x: game.goal.x + OFFSET, y: game.goal.y + OFFSET };
-        if let Some(solution) = game.solve() {
-            total += solution.get_cost();
-        }
-    }
-    return total;
-}
\ No newline at end of file
+    let games: ClawGames = full.parse().expect("Input should parse");
+    games.with_goal_offset(PART2_GOAL_OFFSET).expect("Goals should fit in a usize").total_cost()
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use crate::day14::frames::Frame;
+use crate::day14::{safety_factor_after_n_seconds, Robot, Vector2D};
+use crate::utils::gcd;
+
+/// How tightly the robots cluster along a single axis, lower is tighter
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum AxisScore {
+    /// Only drops if the picture is near the middle, a cluster off to the side adds spread instead
+    Variance,
+    /// Shannon entropy of how many robots are in each column or row
+    #[default]
+    Entropy,
+}
+impl AxisScore {
+    pub fn score(&self, coordinates: &[i64]) -> f64 {
+        if coordinates.is_empty() {
+            return 0.0;
+        }
+        let n = coordinates.len() as f64;
+        match self {
+            AxisScore::Variance => {
+                let mean = coordinates.iter().sum::<i64>() as f64 / n;
+                coordinates.iter().map(|c| (*c as f64 - mean).powi(2)).sum::<f64>() / n
+            }
+            AxisScore::Entropy => {
+                let mut counts: HashMap<i64, usize> = HashMap::new();
+                for c in coordinates {
+                    *counts.entry(*c).or_default() += 1;
+                }
+                counts.values().map(|count| {
+                    let p = *count as f64 / n;
+                    -p * p.log2()
+                }).sum()
+            }
+        }
+    }
+}
+
+/// How much a whole frame looks like a picture, these can't be split up per axis
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FrameScore {
+    /// Most robots in one quadrant gives the lowest safety factor
+    SafetyFactor,
+    /// Size of the largest group of touching robots
+    LargestComponent,
+}
+
+/// The first second at which the robots form a picture, found by scoring each axis on its own.
+///
+/// A robot's x coordinate repeats every `width` seconds and its y coordinate every `height`
+/// seconds. So the tightest x clustering is found in the first `width` seconds, the tightest y
+/// clustering in the first `height` seconds, and the Chinese remainder theorem gives the second
+/// where both happen at once. That's only `width + height` positions per robot.
+///
+/// Returns None for an empty swarm, or if width and height aren't coprime. Then the two seconds
+/// either never line up or line up more than once within the period, and neither tells which
+/// second shows the picture
+pub fn find_picture(robots: &[Robot], width: usize, height: usize, score: AxisScore) -> Option<u64> {
+    if robots.is_empty() || gcd(width, height) != 1 {
+        return None;
+    }
+    let best_second = |size: usize, axis: fn(Vector2D) -> i64| {
+        (0..size)
+            .map(|second| {
+                let coordinates: Vec<i64> = robots.iter().map(|r| axis(r.position_at(second as u64, width, height))).collect();
+                (score.score(&coordinates), second)
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, second)| second)
+            .unwrap_or(0)
+    };
+    let best_x = best_second(width, |position| position.x);
+    let best_y = best_second(height, |position| position.y);
+    chinese_remainder(best_x, width, best_y, height).map(|second| second as u64)
+}
+
+/// Scores every second in `0..seconds` as a whole frame and returns the best one.
+/// Slower than [find_picture], but doesn't assume the picture is compact along both axes
+pub fn scan_frames(robots: &[Robot], width: usize, height: usize, seconds: u64, score: FrameScore) -> Option<u64> {
+    let mut best: Option<(u128, u64)> = None;
+    for second in 0..seconds {
+        // Lower is better for both, so the component size is flipped
+        let frame_score = match score {
+            FrameScore::SafetyFactor => safety_factor_after_n_seconds(robots, second, width, height),
+            FrameScore::LargestComponent => {
+                let positions = robots.iter().map(|r| r.position_at(second, width, height)).map(|p| (p.x as usize, p.y as usize));
+                u128::MAX - Frame::new(width, height, positions).largest_component() as u128
+            }
+        };
+        if best.is_none_or(|(best_score, _)| frame_score < best_score) {
+            best = Some((frame_score, second));
+        }
+    }
+    best.map(|(_, second)| second)
+}
+
+/// The smallest t with `t % p == a` and `t % q == b`, or None if there is none
+pub fn chinese_remainder(a: usize, p: usize, b: usize, q: usize) -> Option<usize> {
+    let lcm = p / gcd(p, q) * q;
+    (a % p..lcm).step_by(p).find(|t| t % q == b % q)
+}
+
+#[cfg(test)]
+const WIDTH: usize = 101;
+#[cfg(test)]
+const HEIGHT: usize = 103;
+
+/// A swarm where `picture` robots are packed into a 10x10 square with its top left `corner` at
+/// second `at`, and `noise` robots are scattered at random
+#[cfg(test)]
+fn swarm_with_picture(picture: usize, noise: usize, corner: (i64, i64), at: i64, seed: u64) -> Vec<Robot> {
+    let mut rng = crate::utils::TestRng::new(seed);
+    let mut random = |n: usize| rng.below(n as u64) as i64;
+    let (width, height) = (WIDTH as i64, HEIGHT as i64);
+    (0..picture + noise).map(|i| {
+        let velocity = Vector2D { x: random(201) - 100, y: random(201) - 100 };
+        let position = if i < picture {
+            let (x, y) = (corner.0 + i as i64 % 10, corner.1 + (i as i64 / 10) % 10);
+            Vector2D { x: (x - velocity.x * at).rem_euclid(width), y: (y - velocity.y * at).rem_euclid(height) }
+        } else {
+            Vector2D { x: random(WIDTH), y: random(HEIGHT) }
+        };
+        Robot { position, velocity }
+    }).collect()
+}
+
+#[test]
+fn test_chinese_remainder() {
+    assert_eq!(chinese_remainder(0, 101, 0, 103), Some(0));
+    assert_eq!(chinese_remainder(2, 3, 3, 5), Some(8));
+    assert_eq!(chinese_remainder(7000 % 101, 101, 7000 % 103, 103), Some(7000));
+    // 4 and 6 share a factor, so odd and even remainders never meet
+    assert_eq!(chinese_remainder(1, 4, 2, 6), None);
+    assert_eq!(chinese_remainder(1, 4, 3, 6), Some(9));
+}
+
+#[test]
+fn test_axis_scores() {
+    assert_eq!(AxisScore::Variance.score(&[3, 3, 3]), 0.0);
+    assert_eq!(AxisScore::Variance.score(&[0, 2]), 1.0);
+    assert_eq!(AxisScore::Entropy.score(&[5, 5, 5, 5]), 0.0);
+    assert_eq!(AxisScore::Entropy.score(&[0, 1, 2, 3]), 2.0);
+}
+
+#[test]
+fn test_find_picture() {
+    for (at, seed) in [(0, 1), (57, 2), (6577, 3), (10_402, 4)] {
+        let robots = swarm_with_picture(150, 350, (45, 46), at, seed);
+        assert_eq!(find_picture(&robots, WIDTH, HEIGHT, AxisScore::Variance), Some(at as u64), "Variance at {at}");
+        assert_eq!(find_picture(&robots, WIDTH, HEIGHT, AxisScore::Entropy), Some(at as u64), "Entropy at {at}");
+    }
+    assert_eq!(find_picture(&[], WIDTH, HEIGHT, AxisScore::Variance), None);
+    // 100 and 102 share a factor of 2, even when the best seconds would happen to line up
+    let robots = swarm_with_picture(150, 350, (45, 46), 0, 7);
+    assert_eq!(find_picture(&robots, 100, 102, AxisScore::Entropy), None);
+}
+
+#[test]
+fn test_scan_frames() {
+    // Packed into the top left quadrant, where the safety factor notices it
+    let robots = swarm_with_picture(100, 150, (10, 10), 321, 5);
+    assert_eq!(scan_frames(&robots, WIDTH, HEIGHT, 400, FrameScore::LargestComponent), Some(321));
+    assert_eq!(scan_frames(&robots, WIDTH, HEIGHT, 400, FrameScore::SafetyFactor), Some(321));
+    assert_eq!(find_picture(&robots, WIDTH, HEIGHT, AxisScore::Entropy), Some(321));
+    let robots = swarm_with_picture(100, 150, (45, 46), 321, 6);
+    assert_eq!(find_picture(&robots, WIDTH, HEIGHT, AxisScore::Variance), Some(321));
+}
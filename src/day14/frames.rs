@@ -0,0 +1,173 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Image formats frames can be written in. PGM and PBM are simple enough to write by hand,
+/// PNG needs the `png` feature
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub enum FrameFormat {
+    /// Binary greymap, robots are white on black
+    #[default]
+    Pgm,
+    /// Binary bitmap, robots are black on white
+    Pbm,
+    #[cfg(feature = "png")]
+    Png,
+}
+impl FrameFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FrameFormat::Pgm => "pgm",
+            FrameFormat::Pbm => "pbm",
+            #[cfg(feature = "png")]
+            FrameFormat::Png => "png",
+        }
+    }
+}
+
+/// Which cells of the room have at least one robot on them, row by row
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub occupied: Vec<bool>,
+}
+impl Frame {
+    pub fn new(width: usize, height: usize, positions: impl IntoIterator<Item = (usize, usize)>) -> Frame {
+        let mut occupied = vec![false; width * height];
+        for (x, y) in positions {
+            occupied[y * width + x] = true;
+        }
+        Frame { width, height, occupied }
+    }
+
+    pub fn to_pgm(&self) -> Vec<u8> {
+        let mut bytes = format!("P5\n{} {}\n255\n", self.width, self.height).into_bytes();
+        bytes.extend(self.occupied.iter().map(|occupied| if *occupied { 255 } else { 0 }));
+        bytes
+    }
+
+    /// Every row is packed into whole bytes, most significant bit first
+    pub fn to_pbm(&self) -> Vec<u8> {
+        let mut bytes = format!("P4\n{} {}\n", self.width, self.height).into_bytes();
+        for row in self.occupied.chunks(self.width) {
+            bytes.extend(row.chunks(8).map(|bits| {
+                bits.iter().enumerate().fold(0u8, |byte, (i, bit)| byte | (u8::from(*bit) << (7 - i)))
+            }));
+        }
+        bytes
+    }
+
+    /// Number of cells in the largest group of occupied cells that touch horizontally or vertically
+    pub fn largest_component(&self) -> usize {
+        let mut seen = vec![false; self.occupied.len()];
+        let mut largest = 0;
+        for start in 0..self.occupied.len() {
+            if !self.occupied[start] || seen[start] {
+                continue;
+            }
+            seen[start] = true;
+            let mut stack = vec![start];
+            let mut size = 0;
+            while let Some(cell) = stack.pop() {
+                size += 1;
+                let (x, y) = (cell % self.width, cell / self.width);
+                let neighbours = [
+                    (x > 0).then(|| cell - 1),
+                    (x + 1 < self.width).then(|| cell + 1),
+                    (y > 0).then(|| cell - self.width),
+                    (y + 1 < self.height).then(|| cell + self.width),
+                ];
+                for next in neighbours.into_iter().flatten() {
+                    if self.occupied[next] && !seen[next] {
+                        seen[next] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+            largest = largest.max(size);
+        }
+        largest
+    }
+}
+
+/// Writes frames into one directory, named after the second they show
+pub struct FrameWriter {
+    dir: PathBuf,
+    format: FrameFormat,
+}
+impl FrameWriter {
+    /// Creates the directory if it doesn't exist yet
+    pub fn new(dir: impl Into<PathBuf>, format: FrameFormat) -> io::Result<FrameWriter> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(FrameWriter { dir, format })
+    }
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn write(&self, second: u64, frame: &Frame) -> io::Result<PathBuf> {
+        let path = self.dir.join(format!("{second}.{}", self.format.extension()));
+        match self.format {
+            FrameFormat::Pgm => fs::write(&path, frame.to_pgm())?,
+            FrameFormat::Pbm => fs::write(&path, frame.to_pbm())?,
+            #[cfg(feature = "png")]
+            FrameFormat::Png => {
+                let pixels = frame.occupied.iter().map(|occupied| if *occupied { 255 } else { 0 }).collect();
+                image::GrayImage::from_raw(frame.width as u32, frame.height as u32, pixels)
+                    .expect("Frame has one pixel per cell")
+                    .save(&path)
+                    .map_err(io::Error::other)?;
+            }
+        }
+        Ok(path)
+    }
+}
+
+#[test]
+fn test_pgm() {
+    let frame = Frame::new(3, 2, [(0, 0), (2, 1)]);
+    let mut expected = b"P5\n3 2\n255\n".to_vec();
+    expected.extend([255, 0, 0, 0, 0, 255]);
+    assert_eq!(frame.to_pgm(), expected);
+}
+
+#[test]
+fn test_pbm() {
+    // Rows longer than a byte carry over into a second, padded byte
+    let frame = Frame::new(10, 2, [(0, 0), (9, 0), (1, 1), (8, 1)]);
+    let mut expected = b"P4\n10 2\n".to_vec();
+    expected.extend([0b1000_0000, 0b0100_0000, 0b0100_0000, 0b1000_0000]);
+    assert_eq!(frame.to_pbm(), expected);
+}
+
+#[test]
+fn test_largest_component() {
+    // Only horizontal and vertical neighbours count, the diagonal robot is on its own
+    let frame = Frame::new(4, 3, [(0, 0), (1, 0), (1, 1), (3, 0), (2, 2), (3, 2)]);
+    assert_eq!(frame.largest_component(), 3);
+    assert_eq!(Frame::new(2, 2, []).largest_component(), 0);
+}
+
+#[test]
+fn test_writer() {
+    let dir = std::env::temp_dir().join(format!("day14-frames-{}", std::process::id()));
+    let writer = FrameWriter::new(&dir, FrameFormat::Pbm).expect("Temp dir should be writable");
+    let path = writer.write(7, &Frame::new(2, 2, [(1, 1)])).expect("Frame should be written");
+    assert_eq!(path, dir.join("7.pbm"));
+    assert_eq!(fs::read(&path).unwrap(), b"P4\n2 2\n\x00\x40".to_vec());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "png")]
+#[test]
+fn test_png_writer() {
+    let dir = std::env::temp_dir().join(format!("day14-png-frames-{}", std::process::id()));
+    let writer = FrameWriter::new(&dir, FrameFormat::Png).expect("Temp dir should be writable");
+    let path = writer.write(1, &Frame::new(3, 3, [(1, 1)])).expect("Frame should be written");
+    let image = image::open(&path).expect("PNG should load").into_luma8();
+    assert_eq!(image.dimensions(), (3, 3));
+    assert_eq!(image.get_pixel(1, 1).0, [255]);
+    assert_eq!(image.get_pixel(0, 1).0, [0]);
+    fs::remove_dir_all(&dir).unwrap();
+}
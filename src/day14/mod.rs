@@ -1,25 +1,39 @@
 use std::fmt::{Display, Formatter};
-use std::fs;
-use std::io::{stdin, Read};
-use image::{GrayImage, ImageBuffer, Rgb};
-use crate::utils::read_input_file;
-use nom::{Complete, IResult, Parser};
+use std::io;
+use std::path::PathBuf;
+use crate::day14::detect::AxisScore;
+use crate::day14::frames::{Frame, FrameFormat, FrameWriter};
+use crate::utils::{gcd, read_input_file};
+use nom::{IResult, Parser};
+
+pub mod detect;
+pub mod frames;
 
 const DAY: &str = "day14";
+const WIDTH: usize = 101;
+const HEIGHT: usize = 103;
+/// Where part 2 writes its frames, unless overridden by the environment variable below
+const DEFAULT_FRAMES_DIR: &str = "target/day14/frames";
+const FRAMES_DIR_VAR: &str = "DAY14_FRAMES_DIR";
+#[cfg(feature = "png")]
+const FRAMES_FORMAT: FrameFormat = FrameFormat::Png;
+#[cfg(not(feature = "png"))]
+const FRAMES_FORMAT: FrameFormat = FrameFormat::Pgm;
 
-#[derive(PartialEq)]
-struct Vector2D {
-    x: i64,
-    y: i64,
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Vector2D {
+    pub x: i64,
+    pub y: i64,
 }
 impl Display for Vector2D {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "({},{})", self.x, self.y)
     }
 }
-struct Robot {
-    position: Vector2D,
-    velocity: Vector2D,
+#[derive(Clone)]
+pub struct Robot {
+    pub position: Vector2D,
+    pub velocity: Vector2D,
 }
 impl Display for Robot {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -70,77 +84,126 @@ fn parse_input(str: &str) -> Result<Input, nom::Err<nom::error::Error<&str>>> {
 
     Ok(all_consuming(separated_list1(newline, robot)).parse(str)?.1)
 }
-fn safety_factor_after_n_seconds(input: &Input, seconds: u64, width: i128, height: i128) -> u128 {
+/// Robots in each quadrant of the room, with y pointing down
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy)]
+pub struct Quadrants {
+    pub top_left: u64,
+    pub top_right: u64,
+    pub bottom_left: u64,
+    pub bottom_right: u64,
+    /// Robots exactly on the middle row or column, which aren't in any quadrant
+    pub middle: u64,
+}
+impl Quadrants {
+    pub fn safety_factor(&self) -> u128 {
+        [self.top_left, self.top_right, self.bottom_left, self.bottom_right].iter().map(|q| *q as u128).product()
+    }
+}
+
+/// After this many seconds every robot is back where it started, whatever its velocity
+pub fn period(width: usize, height: usize) -> u64 {
+    (width / gcd(width, height) * height) as u64
+}
+
+pub fn quadrants_after_n_seconds(input: &[Robot], seconds: u64, width: usize, height: usize) -> Quadrants {
     use std::cmp::Ordering::{Greater, Less};
-    let mut q1: u128 = 0;
-    let mut q2: u128 = 0;
-    let mut q3: u128 = 0;
-    let mut q4: u128 = 0;
+    let mut quadrants = Quadrants::default();
     for robot in input {
-        // i64 -> i128 = safe
-        // and robots are always within a 101x103 square anyway
-        let start_x = robot.position.x as i128;
-        let start_y = robot.position.y as i128;
-        // multiplication is checked, modulus can only make the number smaller
-        let mod_dx = ((robot.velocity.x as i128 % width)).checked_mul(seconds as i128).expect("Overflow for dx") % width;
-        let mod_dy = ((robot.velocity.y as i128 % height)).checked_mul(seconds as i128).expect("Overflow for dy") % height;
-        // start position is small (within 0..103), width and height are 101,103, no chance of overflow for i128
-        let mut end_x = (start_x + mod_dx) % (width);
-        let mut end_y = (start_y + mod_dy) % (height);
-
-        while end_x < 0 {
-            end_x += width;
-        }
-        while end_y < 0 {
-            end_y += height;
-        }
-        match (end_x.cmp(&(width/2)), end_y.cmp(&(height/2))) {
-            (Greater, Greater) => {q1 += 1;},
-            (Greater, Less) => {q2 += 1;},
-            (Less, Greater) => {q3 += 1;},
-            (Less, Less) => {q4 += 1},
-            _ => {
-                // Robot is exactly in the middle on at least one axis, does not contribute to safety
-            }
-        }
+        let Vector2D { x, y } = robot.position_at(seconds, width, height);
+        let quadrant = match (x.cmp(&(width as i64 / 2)), y.cmp(&(height as i64 / 2))) {
+            (Less, Less) => &mut quadrants.top_left,
+            (Greater, Less) => &mut quadrants.top_right,
+            (Less, Greater) => &mut quadrants.bottom_left,
+            (Greater, Greater) => &mut quadrants.bottom_right,
+            _ => &mut quadrants.middle,
+        };
+        *quadrant += 1;
     }
-    return q1 * q2 * q3 * q4;
+    quadrants
+}
+pub fn safety_factor_after_n_seconds(input: &[Robot], seconds: u64, width: usize, height: usize) -> u128 {
+    quadrants_after_n_seconds(input, seconds, width, height).safety_factor()
 }
 impl Robot {
-    fn run_n_seconds(&mut self, seconds: usize, width: usize, height: usize) {
-        let Vector2D{mut x,mut y} = self.position;
-        let Vector2D{x: dx,y: dy} = self.velocity;
-        for _ in 0..seconds {
-            x = (x + dx) % (width as i64);
-            y = (y + dy) % (height as i64);
+    /// Where the robot is after the given number of seconds, wrapping around the edges of the room.
+    /// Only the seconds modulo the width and height matter, so any number of seconds works
+    pub fn position_at(&self, seconds: u64, width: usize, height: usize) -> Vector2D {
+        let axis = |position: i64, velocity: i64, size: usize| {
+            let size = size as i128;
+            (position as i128 + velocity as i128 * (seconds as i128 % size)).rem_euclid(size) as i64
+        };
+        Vector2D {
+            x: axis(self.position.x, self.velocity.x, width),
+            y: axis(self.position.y, self.velocity.y, height),
         }
-        while x < 0 {
-            x += width as i64;
-        }
-        while y < 0 {
-            y += height as i64;
-        }
-        self.position = Vector2D { x, y };
     }
-}
-fn sim_iter(robot: &Robot, seconds: u64, width: i64, height: i64) -> (i128, i128) {
-    let Vector2D{mut x,mut y} = robot.position;
-    for _ in 0..seconds {
-        x = (x + robot.velocity.x) % width;
-        y = (y + robot.velocity.y) % height;
+    pub fn run_n_seconds(&mut self, seconds: u64, width: usize, height: usize) {
+        self.position = self.position_at(seconds, width, height);
     }
-    while x < 0 {
-        x += width;
+}
+#[cfg(test)]
+const DEMO: &str = "p=0,4 v=3,-3
+p=6,3 v=-1,-3
+p=10,3 v=-1,2
+p=2,0 v=2,-1
+p=0,0 v=1,3
+p=3,0 v=-2,-2
+p=7,6 v=-1,-3
+p=3,0 v=-1,-2
+p=9,3 v=2,3
+p=7,3 v=-1,2
+p=2,4 v=2,-3
+p=9,5 v=-3,-3";
+
+#[test]
+fn test_position_at() {
+    let robot = parse_robot("p=2,4 v=2,-3").unwrap().1;
+    let path: Vec<Vector2D> = (0..=5).map(|t| robot.position_at(t, 11, 7)).collect();
+    let expected = [(2, 4), (4, 1), (6, 5), (8, 2), (10, 6), (1, 3)].map(|(x, y)| Vector2D { x, y });
+    assert_eq!(path, expected);
+    // Far beyond anything that could be stepped through one second at a time
+    let period = period(11, 7);
+    assert_eq!(period, 77);
+    assert_eq!(robot.position_at(u64::MAX - u64::MAX % period, 11, 7), robot.position);
+    assert_eq!(robot.position_at(u64::MAX, 11, 7), robot.position_at(u64::MAX % period, 11, 7));
+}
+
+#[test]
+fn test_quadrants() {
+    let robots = parse_input(DEMO).unwrap();
+    let quadrants = quadrants_after_n_seconds(&robots, 100, 11, 7);
+    assert_eq!(quadrants, Quadrants { top_left: 1, top_right: 3, bottom_left: 4, bottom_right: 1, middle: 3 });
+    assert_eq!(quadrants.safety_factor(), 12);
+    assert_eq!(quadrants_after_n_seconds(&robots, 100 + period(11, 7), 11, 7), quadrants);
+}
+
+#[test]
+fn test_period() {
+    assert_eq!(period(101, 103), 10403);
+    // Rooms that share a factor come round sooner
+    assert_eq!(period(4, 6), 12);
+    let robots = parse_input(DEMO).unwrap();
+    for t in 0..12 {
+        for robot in &robots {
+            assert_eq!(robot.position_at(t, 4, 6), robot.position_at(t + 12, 4, 6));
+        }
     }
-    while y < 0 {
-        y += height;
+}
+#[test]
+fn test_write_frames() {
+    let robots = parse_input(DEMO).unwrap();
+    let dir = std::env::temp_dir().join(format!("day14-write-frames-{}", std::process::id()));
+    let paths = write_frames(&robots, 11, 7, &dir, [0, 100], FrameFormat::Pbm).expect("Temp dir should be writable");
+    assert_eq!(paths, vec![dir.join("0.pbm"), dir.join("100.pbm")]);
+    for (path, second) in paths.iter().zip([0, 100]) {
+        let positions = robots.iter().map(|r| r.position_at(second, 11, 7)).map(|p| (p.x as usize, p.y as usize));
+        assert_eq!(std::fs::read(path).unwrap(), Frame::new(11, 7, positions).to_pbm());
     }
-    return (x as i128, y as i128)
+    std::fs::remove_dir_all(&dir).unwrap();
 }
 #[test]
 fn test_part1() {
-    let demo = read_input_file(DAY, "demo.txt");
-    let robots = parse_input(&demo);
+    let robots = parse_input(DEMO);
     match robots {
         Err(e) => panic!("Failed to parse robots: {}", e),
         Ok(robots) => {
@@ -172,7 +235,7 @@ fn test_part2_iter_method_works() {
 
     println!("{}", robots.first().unwrap());
     let mut robots = robots;
-    for x in (0..10) {
+    for _ in 0..10 {
         robots.iter_mut().for_each(|robot| robot.run_n_seconds(1, 101, 103))
     }
     println!("{}", robots.first().unwrap());
@@ -189,52 +252,42 @@ pub fn part1() -> u128 {
     let robots = parse_input(&full);
     match robots {
         Err(e) => panic!("Failed to parse robots: {}", e),
-        Ok(robots) => {
-            return safety_factor_after_n_seconds(&robots, 100, 101, 103);
-        }
+        Ok(robots) => safety_factor_after_n_seconds(&robots, 100, WIDTH, HEIGHT),
     }
 }
+/// The directory from `DAY14_FRAMES_DIR`, or `target/day14/frames`
+pub fn frames_dir() -> PathBuf {
+    std::env::var_os(FRAMES_DIR_VAR).map_or_else(|| PathBuf::from(DEFAULT_FRAMES_DIR), PathBuf::from)
+}
+
+/// Draws the room at each of the given seconds into `dir`, one file per second, so that a picture
+/// can be checked by eye. Returns the paths of the frames
+pub fn write_frames(robots: &[Robot], width: usize, height: usize, dir: impl Into<PathBuf>, seconds: impl IntoIterator<Item = u64>, format: FrameFormat) -> io::Result<Vec<PathBuf>> {
+    let writer = FrameWriter::new(dir, format)?;
+    seconds.into_iter()
+        .map(|second| {
+            let positions = robots.iter().map(|r| r.position_at(second, width, height)).map(|p| (p.x as usize, p.y as usize));
+            writer.write(second, &Frame::new(width, height, positions))
+        })
+        .collect()
+}
+
+/// Writes the frame part 2 picks, and the seconds either side of it, into [frames_dir].
+/// They are PNGs with the `png` feature, and PGMs otherwise
+pub fn write_part2_frames() -> io::Result<Vec<PathBuf>> {
+    let full = read_input_file(DAY, "full.txt");
+    let robots = parse_input(&full).unwrap_or_else(|e| panic!("Failed to parse robots: {}", e));
+    let second = detect::find_picture(&robots, WIDTH, HEIGHT, AxisScore::default()).expect("101 and 103 are coprime");
+    write_frames(&robots, WIDTH, HEIGHT, frames_dir(), second.saturating_sub(1)..=second + 1, FRAMES_FORMAT)
+}
+
+/// The first second at which the robots show a christmas tree
 pub fn part2() -> u128 {
-    const WIDTH: usize = 101;
-    const HEIGHT: usize = 103;
     let full = read_input_file(DAY, "full.txt");
     let robots = parse_input(&full);
     match robots {
         Err(e) => panic!("Failed to parse robots: {}", e),
-        Ok(mut robots) => {
-            for i in 1..10000 {
-                robots.iter_mut().for_each(|r| r.run_n_seconds(1, 101, 103));
-                println!("{} seconds have elapsed:", i);
-
-                // a default (black) image containing Rgb values
-                let mut image = GrayImage::new(WIDTH as u32, HEIGHT as u32);
-
-                for r in &robots {
-                    let pixel = image.get_pixel_mut(r.position.x as u32, r.position.y as u32);
-                    pixel.0[0] = 255;
-                }
-                // write it out to a file
-                fs::create_dir("src/day14/images"); // if it fails, assumje dir already exists
-                image.save(format!("src/day14/images/{}.png", i)).unwrap();
-
-            }
-            0
-        }
-    }
-}
-
-
-
-fn display_map(robots: &Input, width: i64, height: i64) {
-    for y in 0..height {
-        for x in 0..width {
-            match robots.iter().filter(|r| r.position == Vector2D { x, y }).count() {
-                0 => print!("."),
-                n if 0 <= n && n <= 9 => print!("{}", n),
-                _ => print!("#")
-            }
-        }
-        println!();
+        Ok(robots) => detect::find_picture(&robots, WIDTH, HEIGHT, AxisScore::default()).expect("101 and 103 are coprime") as u128,
     }
 }
 
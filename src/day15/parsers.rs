@@ -1,13 +1,71 @@
+use std::str::FromStr;
 use nom::Parser;
 use nom::IResult;
 use crate::day15::types;
 
+/// One character of the map. Halves of a wide box only make sense in pairs
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+enum Cell {
+    Tile(types::Tile),
+    BoxLeft,
+    BoxRight,
+}
+
 /// `@`, `#`, `O`, `.` 
 fn parse_tile(str: &str) -> IResult<&str, types::Tile> {
     nom::character::complete::one_of("#O.@")
         .map_res(|c| types::Tile::try_from(c))
         .parse(str)
 }
+/// A tile, or `[` or `]` from the widened map
+fn parse_cell(str: &str) -> IResult<&str, Cell> {
+    let one_of = nom::character::complete::one_of;
+    let tile = parse_tile.map(Cell::Tile);
+    let half = one_of("[]").map(|c| if c == '[' { Cell::BoxLeft } else { Cell::BoxRight });
+
+    nom::branch::alt((tile, half)).parse(str)
+}
+/// A row where every `[` is directly followed by a `]`
+fn parse_row(str: &str) -> IResult<&str, Vec<Cell>> {
+    nom::multi::many1(parse_cell)
+        .map_res(|row| {
+            let mut expect_right = false;
+            for cell in &row {
+                match (cell, expect_right) {
+                    (Cell::BoxLeft, false) => expect_right = true,
+                    (Cell::BoxRight, true) => expect_right = false,
+                    (_, true) | (Cell::BoxRight, false) => return Err(()),
+                    (_, false) => {}
+                }
+            }
+            if expect_right { Err(()) } else { Ok(row) }
+        })
+        .parse(str)
+}
+
+/// Every `[]` becomes one object covering both cells, numbered after the `O` boxes. Fails with the
+/// first cell of a box that couldn't be placed
+fn to_warehouse(rows: Vec<Vec<Cell>>) -> Result<types::Warehouse, types::Point> {
+    let mut wide_boxes = Vec::new();
+    let tiles = rows.into_iter().enumerate()
+        .map(|(y, row)| row.into_iter().enumerate()
+            .map(|(x, cell)| match cell {
+                Cell::Tile(tile) => tile,
+                Cell::BoxLeft => {
+                    let left = types::Point::new(x as isize, y as isize);
+                    wide_boxes.push(vec![left, types::Point::new(left.x + 1, left.y)]);
+                    types::Tile::Empty
+                }
+                Cell::BoxRight => types::Tile::Empty,
+            })
+            .collect())
+        .collect();
+    let mut warehouse = types::Warehouse::from_2d_vec(tiles);
+    for cells in wide_boxes {
+        warehouse.add_object(cells)?;
+    }
+    Ok(warehouse)
+}
 
 /// NB: Vertical line of > not part of map. Rustdoc doesn't allow starting a line with ##, even in
 /// a code block
@@ -20,16 +78,37 @@ fn parse_tile(str: &str) -> IResult<&str, types::Tile> {
 /// > #.....O.#
 /// > #########
 /// ```
+/// The widened map from part 2, with `[]` for boxes, is read the same way
 fn parse_map(str: &str) -> IResult<&str, types::Warehouse> {
     let line_ending = nom::character::complete::line_ending;
     let separated_list1 = nom::multi::separated_list1;
-    let many1 = nom::multi::many1;
-    
-    let tile = parse_tile;
-    let row = many1(tile);
+
+    let row = parse_row;
     let map = separated_list1(line_ending, row);
-    
-    return map.map(types::Warehouse::from_2d_vec).parse(str);
+
+    map.map_res(|rows| {
+        if rows.iter().any(|row| row.len() != rows[0].len()) {
+            return Err(());
+        }
+        to_warehouse(rows).map_err(|_| ())
+    }).parse(str)
+}
+
+/// Just the map, which may end in a newline. Reads what [types::Warehouse]'s `Display` writes
+impl FromStr for types::Warehouse {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let line_ending = nom::character::complete::line_ending;
+        let opt = nom::combinator::opt;
+        let all_consuming = nom::combinator::all_consuming;
+
+        all_consuming((parse_map, opt(line_ending)))
+            .map(|(map, _)| map)
+            .parse(s)
+            .map(|(_, map)| map)
+            .map_err(|_| ())
+    }
 }
 
 /// `^` `>` `v` `<` 
@@ -103,3 +182,83 @@ fn test_parse_input() {
     assert!(map.get_at(&types::Point::new(-1,0)).is_none());
     assert!(map.get_at(&types::Point::new(0,-1)).is_none());
 }
+
+
+#[test]
+fn test_parse_wide_map() {
+    let map = "####################
+##....[]....[]..[]##
+##............[]..##
+##..[][]....[]..[]##
+##....[]@.....[]..##
+##[]##....[]......##
+##[]....[]....[]..##
+##..[][]..[]..[][]##
+##........[]......##
+####################
+";
+    let wh: types::Warehouse = map.parse().expect("Widened map should parse");
+    assert_eq!(wh.to_string(), map);
+    assert_eq!(wh.robot(), Some(types::Point::new(8, 4)));
+    assert_eq!(wh.objects().len(), 21);
+    assert_eq!(wh.object_at(&types::Point::new(6, 1)), wh.object_at(&types::Point::new(7, 1)));
+    // Same as widening the normal map
+    let narrow: types::Warehouse = "##########
+#..O..O.O#
+#......O.#
+#.OO..O.O#
+#..O@..O.#
+#O#..O...#
+#O..O..O.#
+#.OO.O.OO#
+#....O...#
+##########".parse().expect("Normal map should parse");
+    assert_eq!(narrow.widen().to_string(), map);
+    assert_eq!(narrow.widen().gps_sum(), wh.gps_sum());
+}
+
+#[test]
+fn test_parse_wide_map_errors() {
+    assert_eq!("#[]#".parse::<types::Warehouse>().map(|wh| wh.to_string()), Ok(String::from("#[]#\n")));
+    assert!("#[.]#".parse::<types::Warehouse>().is_err());
+    assert!("#]#".parse::<types::Warehouse>().is_err());
+    assert!("#[".parse::<types::Warehouse>().is_err());
+    assert!("#[[]]".parse::<types::Warehouse>().is_err());
+    // Rows have to be the same length, wherever the boxes are
+    assert!("#\n[]".parse::<types::Warehouse>().is_err());
+    assert!("#..\n.".parse::<types::Warehouse>().is_err());
+}
+
+#[test]
+fn test_wide_intermediate_states() {
+    // From the puzzle statement, the widened small example after its first two moves
+    let (wh, moves) = parse_input("##############
+##......##..##
+##..........##
+##....[][]@.##
+##....[]....##
+##..........##
+##############
+
+<v").expect("Should parse successfully").1;
+    let mut wh = wh;
+    let states: Vec<String> = moves.into_iter().map(|mv| {
+        wh.apply(mv);
+        wh.to_string()
+    }).collect();
+    assert_eq!(states, vec!["##############
+##......##..##
+##..........##
+##...[][]@..##
+##....[]....##
+##..........##
+##############
+", "##############
+##......##..##
+##..........##
+##...[][]...##
+##....[].@..##
+##..........##
+##############
+"]);
+}
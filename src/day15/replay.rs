@@ -0,0 +1,267 @@
+use std::fmt::Write;
+use std::io;
+use crate::day15::types::{Direction, MoveOutcome, Point, Warehouse};
+
+/// How many moves apart the stored copies of the warehouse are, seeking replays at most this many
+const KEYFRAME_INTERVAL: usize = 1000;
+
+/// A cell that looks different after a move
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct Change {
+    pub point: Point,
+    pub before: char,
+    pub after: char,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Step {
+    pub direction: Direction,
+    pub outcome: MoveOutcome,
+    /// Compared as displayed, so a wide box moving one cell sideways changes all three cells
+    pub changes: Vec<Change>,
+}
+
+/// Every move of a run with what it did, and the warehouse as it was after any of them
+pub struct Replay {
+    steps: Vec<Step>,
+    /// The warehouse after 0, [KEYFRAME_INTERVAL], 2 * [KEYFRAME_INTERVAL], ... moves
+    keyframes: Vec<Warehouse>,
+}
+
+impl Replay {
+    /// Runs all moves on the warehouse and records them. None if there is no robot to move
+    pub fn record(mut warehouse: Warehouse, moves: &[Direction]) -> Option<Replay> {
+        let mut steps = Vec::with_capacity(moves.len());
+        let mut keyframes = vec![warehouse.clone()];
+        let mut before = warehouse.to_string();
+        for (i, direction) in moves.iter().enumerate() {
+            let outcome = warehouse.apply(*direction)?;
+            let after = warehouse.to_string();
+            steps.push(Step { direction: *direction, outcome, changes: changes(&before, &after) });
+            before = after;
+            if (i + 1) % KEYFRAME_INTERVAL == 0 {
+                keyframes.push(warehouse.clone());
+            }
+        }
+        Some(Replay { steps, keyframes })
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// The warehouse after the first `step` moves, so 0 is where it started.
+    /// None if there weren't that many moves
+    pub fn at(&self, step: usize) -> Option<Warehouse> {
+        if step > self.steps.len() {
+            return None;
+        }
+        let mut warehouse = self.keyframes[step / KEYFRAME_INTERVAL].clone();
+        for s in &self.steps[step - step % KEYFRAME_INTERVAL..step] {
+            warehouse.apply(s.direction);
+        }
+        Some(warehouse)
+    }
+
+    /// The warehouse at each of the given steps with a heading, for reading through or diffing
+    /// against another run. Steps past the end are skipped
+    pub fn frame_dump(&self, steps: &[usize]) -> String {
+        let mut dump = String::new();
+        for step in steps {
+            if let Some(warehouse) = self.at(*step) {
+                writeln!(dump, "{}\n{warehouse}", self.caption(*step)).unwrap();
+            }
+        }
+        dump
+    }
+
+    /// Every move as a terminal animation: clears the screen once, then redraws over it with the
+    /// robot highlighted. Write it to a terminal slowly, or pipe it through `pv -L` to watch it
+    pub fn write_ansi(&self, mut out: impl io::Write) -> io::Result<()> {
+        out.write_all(b"\x1b[2J")?;
+        self.for_each_state(|step, warehouse| out.write_all(self.ansi_frame(step, warehouse, "\n").as_bytes()))
+    }
+
+    /// An asciicast v2 recording, one line of JSON for the header and one per move,
+    /// `frame_delay` seconds apart. Wide enough for the map and every caption
+    pub fn write_asciicast(&self, mut out: impl io::Write, frame_delay: f64) -> io::Result<()> {
+        let first = &self.keyframes[0];
+        let captions = (0..=self.len()).map(|step| self.caption(step).chars().count());
+        let width = captions.fold(first.get_width(), usize::max);
+        writeln!(out, "{{\"version\":2,\"width\":{width},\"height\":{}}}", first.get_height() + 1)?;
+        writeln!(out, "[0.0,\"o\",{}]", json_string("\x1b[2J"))?;
+        self.for_each_state(|step, warehouse| {
+            // Terminals need a carriage return to get back to the start of the line
+            let frame = json_string(&self.ansi_frame(step, warehouse, "\r\n"));
+            writeln!(out, "[{:.3},\"o\",{frame}]", step as f64 * frame_delay)
+        })
+    }
+
+    /// Calls `f` with the warehouse after 0, 1, ... moves, replaying them one by one,
+    /// and stops at the first error
+    fn for_each_state(&self, mut f: impl FnMut(usize, &Warehouse) -> io::Result<()>) -> io::Result<()> {
+        let mut warehouse = self.keyframes[0].clone();
+        f(0, &warehouse)?;
+        for (i, step) in self.steps.iter().enumerate() {
+            warehouse.apply(step.direction);
+            f(i + 1, &warehouse)?;
+        }
+        Ok(())
+    }
+
+    fn caption(&self, step: usize) -> String {
+        match step.checked_sub(1).map(|i| &self.steps[i]) {
+            None => format!("Initial state ({} moves)", self.len()),
+            Some(s) => format!("Move {step}/{}: {} {}", self.len(), s.direction, s.outcome),
+        }
+    }
+
+    fn ansi_frame(&self, step: usize, warehouse: &Warehouse, newline: &str) -> String {
+        let mut frame = String::from("\x1b[H");
+        for line in warehouse.to_string().lines() {
+            frame.push_str(&line.replace('@', "\x1b[1;31m@\x1b[0m"));
+            frame.push_str(newline);
+        }
+        frame.push_str("\x1b[2K");
+        frame.push_str(&self.caption(step));
+        frame.push_str(newline);
+        frame
+    }
+}
+
+fn changes(before: &str, after: &str) -> Vec<Change> {
+    before.lines().zip(after.lines()).enumerate()
+        .flat_map(|(y, (before, after))| {
+            before.chars().zip(after.chars()).enumerate()
+                .filter(|(_, (before, after))| before != after)
+                .map(move |(x, (before, after))| Change { point: Point::new(x as isize, y as isize), before, after })
+        })
+        .collect()
+}
+
+fn json_string(str: &str) -> String {
+    let mut json = String::from("\"");
+    for c in str.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            c if c.is_control() => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+fn demo_replay(wide: bool) -> Replay {
+    let (warehouse, moves) = crate::day15::parsers::parse_input("#######
+#...#.#
+#.....#
+#..OO@#
+#..O..#
+#.....#
+#######
+
+<vv<<^^<<^^").unwrap().1;
+    Replay::record(if wide { warehouse.widen() } else { warehouse }, &moves).expect("The demo has a robot")
+}
+
+#[test]
+fn test_record() {
+    let replay = demo_replay(true);
+    assert_eq!(replay.len(), 11);
+    let first = &replay.steps()[0];
+    assert_eq!(first.direction, Direction::West);
+    assert_eq!(first.outcome, MoveOutcome::Pushed(2));
+    // The robot and two wide boxes each move one cell left
+    assert_eq!(first.changes, vec![
+        Change { point: Point::new(5, 3), before: '.', after: '[' },
+        Change { point: Point::new(6, 3), before: '[', after: ']' },
+        Change { point: Point::new(7, 3), before: ']', after: '[' },
+        Change { point: Point::new(8, 3), before: '[', after: ']' },
+        Change { point: Point::new(9, 3), before: ']', after: '@' },
+        Change { point: Point::new(10, 3), before: '@', after: '.' },
+    ]);
+    assert_eq!(replay.steps()[1].outcome, MoveOutcome::Moved);
+    assert_eq!(replay.steps()[1].changes.len(), 2);
+    assert_eq!(replay.at(11).unwrap().gps_sum(), 618);
+    assert!(replay.at(12).is_none());
+
+    // Nothing to replay without a robot, unless there are no moves either
+    let warehouse: Warehouse = "#.O#".parse().unwrap();
+    assert!(Replay::record(warehouse.clone(), &[Direction::West]).is_none());
+    assert_eq!(Replay::record(warehouse, &[]).map(|replay| replay.len()), Some(0));
+}
+
+#[test]
+fn test_seek() {
+    // Long enough for a few keyframes
+    let (warehouse, moves) = crate::day15::parsers::parse_input("########\n#..O.O.#\n##@.O..#\n#...O..#\n#.#.O..#\n#...O..#\n#......#\n########\n\n<^^>>>vv<v>>v<<").unwrap().1;
+    let moves: Vec<Direction> = moves.iter().copied().cycle().take(2 * KEYFRAME_INTERVAL + 7).collect();
+    let replay = Replay::record(warehouse.clone(), &moves).expect("The map has a robot");
+    let mut expected = warehouse;
+    for step in 0..=moves.len() {
+        if step > 0 {
+            expected.apply(moves[step - 1]);
+        }
+        if step % 97 == 0 || step % KEYFRAME_INTERVAL < 2 || step == moves.len() {
+            assert_eq!(replay.at(step).as_ref(), Some(&expected), "Step {step}");
+        }
+    }
+}
+
+#[test]
+fn test_frame_dump() {
+    let dump = demo_replay(false).frame_dump(&[0, 1, 99]);
+    assert_eq!(dump, "Initial state (11 moves)
+#######
+#...#.#
+#.....#
+#..OO@#
+#..O..#
+#.....#
+#######
+
+Move 1/11: < pushed 2 objects
+#######
+#...#.#
+#.....#
+#.OO@.#
+#..O..#
+#.....#
+#######
+
+");
+}
+
+#[test]
+fn test_exports() {
+    let replay = demo_replay(false);
+    let mut ansi = Vec::new();
+    replay.write_ansi(&mut ansi).expect("Writing to a Vec can't fail");
+    let ansi = String::from_utf8(ansi).unwrap();
+    assert!(ansi.starts_with("\x1b[2J\x1b[H#######\n"));
+    assert_eq!(ansi.matches("\x1b[H").count(), 12);
+    assert!(ansi.contains("#..OO\x1b[1;31m@\x1b[0m#\n"));
+
+    let mut cast = Vec::new();
+    replay.write_asciicast(&mut cast, 0.5).expect("Writing to a Vec can't fail");
+    let cast = String::from_utf8(cast).unwrap();
+    let lines: Vec<&str> = cast.lines().collect();
+    assert_eq!(lines.len(), 1 + 1 + 12);
+    // "Move 1/11: < pushed 2 objects" is the longest caption, wider than the map
+    assert_eq!(lines[0], "{\"version\":2,\"width\":29,\"height\":8}");
+    assert_eq!(lines[1], "[0.0,\"o\",\"\\u001b[2J\"]");
+    assert!(lines[2].starts_with("[0.000,\"o\",\"\\u001b[H#######\\r\\n"));
+    assert!(lines[13].starts_with("[5.500,\"o\","));
+    assert!(lines[13].ends_with("Move 11/11: ^ moved\\r\\n\"]"));
+}
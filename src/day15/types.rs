@@ -1,6 +1,7 @@
-use std::fmt::Display;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy, Hash)]
 pub struct Point {
     pub x: isize,
     pub y: isize,
@@ -9,9 +10,18 @@ impl Point {
     pub fn new(x: isize, y: isize) -> Point {
         Point { x, y }
     }
+    /// The neighbouring point in the given direction, y points down
+    pub fn step(&self, direction: &Direction) -> Point {
+        match direction {
+            Direction::North => Point::new(self.x, self.y - 1),
+            Direction::East => Point::new(self.x + 1, self.y),
+            Direction::South => Point::new(self.x, self.y + 1),
+            Direction::West => Point::new(self.x - 1, self.y),
+        }
+    }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum Direction {
     North,
     East,
@@ -44,7 +54,7 @@ impl Display for Direction {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum Tile {
     Empty,
     Box,
@@ -76,41 +86,382 @@ impl Display for Tile {
 
 pub type Instructions = Vec<Direction>;
 
-#[derive(Debug, Eq, PartialEq)]
+/// Index into [Warehouse::objects], an object keeps its id for as long as the warehouse exists
+pub type ObjectId = usize;
+
+/// Something that can be pushed around, a polyomino given by the cells it covers.
+/// A puzzle box is a single cell, a widened box two cells next to each other
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Object {
+    pub cells: Vec<Point>,
+}
+
+/// The map is the source of truth for what is where, cells covered by an object are [Tile::Box].
+/// Which object covers such a cell is kept alongside, so pushing one cell of an object moves all of it.
+/// Cells are only changed through [Warehouse::set_at] and friends, which keep the two in step
+#[derive(Debug, Eq, PartialEq, Clone)]
 pub struct Warehouse {
-    pub map: Vec<Tile>,
-    pub width: usize,
+    map: Vec<Tile>,
+    width: usize,
+    /// Where the [Tile::Robot] is, if there is one
+    robot: Option<Point>,
+    objects: Vec<Object>,
+    /// The object covering each cell of the map, if any
+    occupants: Vec<Option<ObjectId>>,
 }
 
 impl Warehouse {
+    /// Every [Tile::Box] becomes a single cell object, numbered row by row
     pub fn from_2d_vec(input: Vec<Vec<Tile>>) -> Warehouse{
-        if let Some(first) = input.iter().next() {
-            Warehouse {
-                width: first.len(),
-                map: input.into_iter().flatten().collect(),
+        let width = input.first().map_or(0, |first| first.len());
+        let map: Vec<Tile> = input.into_iter().flatten().collect();
+        let mut robot = None;
+        let mut objects = Vec::new();
+        let mut occupants = vec![None; map.len()];
+        for (i, tile) in map.iter().enumerate() {
+            let point = Point::new((i % width) as isize, (i / width) as isize);
+            match tile {
+                Tile::Box => {
+                    occupants[i] = Some(objects.len());
+                    objects.push(Object { cells: vec![point] });
+                }
+                Tile::Robot => robot = Some(point),
+                Tile::Empty | Tile::Wall => {}
             }
-        } else {
-            Warehouse { map: Vec::new(), width: 0 }
         }
+        Warehouse { map, width, robot, objects, occupants }
+    }
+    pub fn get_width(&self) -> usize {
+        self.width
     }
     pub fn get_height(&self) -> usize {
-        return self.map.len() / self.width;
+        self.map.len().checked_div(self.width).unwrap_or(0)
     }
 
-    pub fn get_at(&self, point: &Point) -> Option<&Tile> {
+    fn index_of(&self, point: &Point) -> Option<usize> {
         let Point { x, y } = point;
         if *x < 0 { return None }
         if *x >= self.width as isize { return None }
         if *y < 0 { return None }
         if *y >= self.get_height() as isize { return None }
-        return Some(&self.map[*y as usize * self.width + *x as usize]);
+        Some(*y as usize * self.width + *x as usize)
     }
 
-    fn set_at(&mut self, point: &Point, tile: Tile) {
-        let Point { x, y } = point;
-        if *x < 0 { return }
-        if *x >= self.width as isize { return }
-        if *y < 0 { return }
-        if *y >= self.get_height() as isize { return }
+    pub fn get_at(&self, point: &Point) -> Option<&Tile> {
+        self.index_of(point).map(|i| &self.map[i])
+    }
+
+    /// Overwrites a single cell, points off the map are ignored. A cell taken from an object is
+    /// no longer part of it, a new [Tile::Box] is an object of its own, and a new [Tile::Robot]
+    /// replaces the old one
+    pub fn set_at(&mut self, point: &Point, tile: Tile) {
+        let Some(index) = self.index_of(point) else { return };
+        if let Some(id) = self.occupants[index].take() {
+            self.objects[id].cells.retain(|cell| cell != point);
+        }
+        if self.robot == Some(*point) {
+            self.robot = None;
+        }
+        self.map[index] = tile;
+        match tile {
+            Tile::Box => {
+                self.occupants[index] = Some(self.objects.len());
+                self.objects.push(Object { cells: vec![*point] });
+            }
+            Tile::Robot => {
+                if let Some(old) = self.robot.replace(*point) {
+                    self.set_at(&old, Tile::Empty);
+                }
+            }
+            Tile::Empty | Tile::Wall => {}
+        }
+    }
+
+    pub fn robot(&self) -> Option<Point> {
+        self.robot
+    }
+
+    pub fn objects(&self) -> &[Object] {
+        &self.objects
+    }
+    pub fn object_at(&self, point: &Point) -> Option<ObjectId> {
+        self.index_of(point).and_then(|i| self.occupants[i])
+    }
+
+    /// Places a new object of any shape. Fails without changing anything if one of its cells is
+    /// off the map or not empty, and returns that cell
+    pub fn add_object(&mut self, cells: Vec<Point>) -> Result<ObjectId, Point> {
+        let mut indices = Vec::new();
+        for cell in &cells {
+            let index = self.index_of(cell).ok_or(*cell)?;
+            if self.map[index] != Tile::Empty || indices.contains(&index) {
+                return Err(*cell);
+            }
+            indices.push(index);
+        }
+        let id = self.objects.len();
+        for index in indices {
+            self.map[index] = Tile::Box;
+            self.occupants[index] = Some(id);
+        }
+        self.objects.push(Object { cells });
+        Ok(id)
+    }
+
+    /// Pushes whatever is in front of `from` one step in the given direction.
+    ///
+    /// Objects push every object their cells run into, so this moves a whole connected set at
+    /// once. It's all or nothing: if any cell of that set would end up in a wall, the robot or off
+    /// the map, nothing moves and that cell is returned. Otherwise returns the ids of the objects
+    /// that moved, which is empty if there was nothing in front of `from`
+    pub fn push(&mut self, from: &Point, direction: &Direction) -> Result<Vec<ObjectId>, Point> {
+        let mut moving: Vec<ObjectId> = Vec::new();
+        let mut seen: HashSet<ObjectId> = HashSet::new();
+        let mut frontier = vec![from.step(direction)];
+        while let Some(cell) = frontier.pop() {
+            match self.get_at(&cell) {
+                None | Some(Tile::Wall) | Some(Tile::Robot) => return Err(cell),
+                Some(Tile::Empty) => {}
+                Some(Tile::Box) => {
+                    let id = self.object_at(&cell).expect("Every box cell belongs to an object");
+                    if seen.insert(id) {
+                        moving.push(id);
+                        frontier.extend(self.objects[id].cells.iter().map(|cell| cell.step(direction)));
+                    }
+                }
+            }
+        }
+
+        // Lift everything up before putting it down again, so objects can move into each other's old cells
+        for id in &moving {
+            for cell in &self.objects[*id].cells {
+                let index = self.index_of(cell).expect("Objects are on the map");
+                self.map[index] = Tile::Empty;
+                self.occupants[index] = None;
+            }
+        }
+        for id in &moving {
+            for cell in self.objects[*id].cells.iter_mut() {
+                *cell = cell.step(direction);
+            }
+            for cell in &self.objects[*id].cells {
+                let index = self.index_of(cell).expect("Checked above that objects stay on the map");
+                self.map[index] = Tile::Box;
+                self.occupants[index] = Some(*id);
+            }
+        }
+        Ok(moving)
+    }
+
+    /// The warehouse for part 2, where everything except the robot is twice as wide.
+    /// Objects keep their ids and every cell of them becomes two
+    pub fn widen(&self) -> Warehouse {
+        let map = self.map.iter()
+            .flat_map(|tile| match tile {
+                Tile::Robot => [Tile::Robot, Tile::Empty],
+                tile => [*tile, *tile],
+            })
+            .collect();
+        let objects: Vec<Object> = self.objects.iter()
+            .map(|object| Object {
+                cells: object.cells.iter()
+                    .flat_map(|cell| [Point::new(cell.x * 2, cell.y), Point::new(cell.x * 2 + 1, cell.y)])
+                    .collect(),
+            })
+            .collect();
+        let mut occupants = vec![None; self.map.len() * 2];
+        let width = self.width * 2;
+        for (id, object) in objects.iter().enumerate() {
+            for cell in &object.cells {
+                occupants[cell.y as usize * width + cell.x as usize] = Some(id);
+            }
+        }
+        let robot = self.robot.map(|robot| Point::new(robot.x * 2, robot.y));
+        Warehouse { map, width, robot, objects, occupants }
+    }
+
+    /// Has the robot try to move one step, pushing anything in its way.
+    /// Returns None if there is no robot
+    pub fn apply(&mut self, direction: Direction) -> Option<MoveOutcome> {
+        let robot = self.robot?;
+        Some(match self.push(&robot, &direction) {
+            Err(cell) => MoveOutcome::Blocked(cell),
+            Ok(pushed) => {
+                self.set_at(&robot.step(&direction), Tile::Robot);
+                if pushed.is_empty() { MoveOutcome::Moved } else { MoveOutcome::Pushed(pushed.len()) }
+            }
+        })
+    }
+
+    /// Sum of the GPS coordinates of all objects, which is 100 times the distance from the top
+    /// plus the distance from the left, measured to the closest edge of the object
+    pub fn gps_sum(&self) -> usize {
+        self.objects.iter()
+            .filter_map(|object| {
+                let top = object.cells.iter().map(|cell| cell.y).min()?;
+                let left = object.cells.iter().map(|cell| cell.x).min()?;
+                Some(100 * top as usize + left as usize)
+            })
+            .sum()
+    }
+}
+
+/// What happened when the robot tried to move
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum MoveOutcome {
+    /// Stepped into an empty cell
+    Moved,
+    /// Moved, and pushed this many objects along
+    Pushed(usize),
+    /// Something didn't budge at this cell, a wall or the edge of the map
+    Blocked(Point),
+}
+impl Display for MoveOutcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveOutcome::Moved => write!(f, "moved"),
+            MoveOutcome::Pushed(1) => write!(f, "pushed 1 object"),
+            MoveOutcome::Pushed(objects) => write!(f, "pushed {objects} objects"),
+            MoveOutcome::Blocked(Point { x, y }) => write!(f, "blocked at ({x},{y})"),
+        }
+    }
+}
+
+/// Objects two cells wide are drawn as `[]`, everything else as in the puzzle input
+impl Display for Warehouse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, tile) in self.map.iter().enumerate() {
+            let point = Point::new((i % self.width) as isize, (i / self.width) as isize);
+            match self.occupants[i].map(|id| self.objects[id].cells.as_slice()) {
+                Some([left, right]) if left.y == right.y && left.x + 1 == right.x => {
+                    write!(f, "{}", if point == *left { '[' } else { ']' })?;
+                }
+                _ => write!(f, "{}", tile)?,
+            }
+            if (i + 1) % self.width == 0 {
+                writeln!(f)?;
+            }
+        }
+        Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+fn warehouse(rows: &[&str]) -> Warehouse {
+    Warehouse::from_2d_vec(rows.iter().map(|row| row.chars().map(|c| Tile::try_from(c).unwrap()).collect()).collect())
+}
+#[cfg(test)]
+fn rows(warehouse: &Warehouse) -> Vec<String> {
+    warehouse.to_string().lines().map(String::from).collect()
+}
+
+#[test]
+fn test_push_row_of_boxes() {
+    let mut wh = warehouse(&["#@OO.O#"]);
+    assert_eq!(wh.push(&Point::new(1, 0), &Direction::East), Ok(vec![0, 1]));
+    assert_eq!(rows(&wh), vec!["#@.OOO#"]);
+    // The boxes keep their ids while moving
+    assert_eq!(wh.object_at(&Point::new(3, 0)), Some(0));
+    assert_eq!(wh.objects()[1].cells, vec![Point::new(4, 0)]);
+    // Nothing in front, so nothing moves
+    assert_eq!(wh.push(&Point::new(1, 0), &Direction::East), Ok(vec![]));
+    // The boxes are up against the wall
+    assert_eq!(wh.push(&Point::new(2, 0), &Direction::East), Err(Point::new(6, 0)));
+    assert_eq!(wh.push(&Point::new(1, 0), &Direction::North), Err(Point::new(1, -1)));
+    assert_eq!(rows(&wh), vec!["#@.OOO#"]);
+}
+
+#[test]
+fn test_widen() {
+    let mut wh = warehouse(&["#.O@"]).widen();
+    assert_eq!(rows(&wh), vec!["##..[]@."]);
+    assert_eq!(wh.objects()[0].cells, vec![Point::new(4, 0), Point::new(5, 0)]);
+    assert_eq!(wh.push(&Point::new(6, 0), &Direction::West), Ok(vec![0]));
+    assert_eq!(rows(&wh), vec!["##.[].@."]);
+    assert_eq!(wh.object_at(&Point::new(3, 0)), Some(0));
+    assert_eq!(wh.object_at(&Point::new(5, 0)), None);
+}
+
+#[test]
+fn test_push_is_all_or_nothing() {
+    // Pushing the wide box at the bottom up pushes both wide boxes above it
+    let empty = warehouse(&[".......", ".......", ".......", "......."]);
+    let mut wh = empty.clone();
+    let top_left = wh.add_object(vec![Point::new(3, 1), Point::new(4, 1)]).unwrap();
+    let top_right = wh.add_object(vec![Point::new(5, 1), Point::new(6, 1)]).unwrap();
+    let bottom = wh.add_object(vec![Point::new(4, 2), Point::new(5, 2)]).unwrap();
+    let mut blocked = wh.clone();
+
+    let mut moved = wh.push(&Point::new(4, 3), &Direction::North).unwrap();
+    moved.sort();
+    assert_eq!(moved, vec![top_left, top_right, bottom]);
+    assert_eq!(rows(&wh), vec!["...[][]", "....[].", ".......", "......."]);
+
+    // With a wall above the right one, the left one can't move either
+    blocked.set_at(&Point::new(6, 0), Tile::Wall);
+    let before = blocked.clone();
+    assert_eq!(blocked.push(&Point::new(4, 3), &Direction::North), Err(Point::new(6, 0)));
+    assert_eq!(blocked, before);
+}
+
+#[test]
+fn test_push_any_shape() {
+    let mut wh = warehouse(&[
+        "#######",
+        "#.....#",
+        "#.....#",
+        "#..@..#",
+        "#######",
+    ]);
+    // An L, and a box resting in its corner
+    let l = wh.add_object(vec![Point::new(2, 1), Point::new(2, 2), Point::new(3, 2)]).unwrap();
+    let small = wh.add_object(vec![Point::new(3, 1)]).unwrap();
+    assert_eq!(wh.add_object(vec![Point::new(3, 1)]), Err(Point::new(3, 1)));
+    assert_eq!(wh.add_object(vec![Point::new(4, 1), Point::new(4, 0)]), Err(Point::new(4, 0)));
+
+    assert_eq!(wh.push(&Point::new(1, 2), &Direction::East), Ok(vec![l, small]));
+    assert_eq!(rows(&wh)[1..3], ["#..OO.#", "#..OO.#"]);
+    // Pushing the small box down pushes the L into the robot
+    assert_eq!(wh.push(&Point::new(4, 0), &Direction::South), Err(Point::new(3, 3)));
+    assert_eq!(wh.object_at(&Point::new(4, 1)), Some(small));
+}
+
+#[test]
+fn test_set_at() {
+    let mut wh = warehouse(&["@.O.", "...."]);
+    wh.set_at(&Point::new(1, 0), Tile::Box);
+    assert_eq!(wh.object_at(&Point::new(1, 0)), Some(1));
+    // Only one robot at a time
+    wh.set_at(&Point::new(3, 1), Tile::Robot);
+    assert_eq!(wh.robot(), Some(Point::new(3, 1)));
+    assert_eq!(rows(&wh), vec![".OO.", "...@"]);
+    wh.set_at(&Point::new(2, 0), Tile::Empty);
+    assert_eq!(wh.objects()[0].cells, vec![]);
+    assert_eq!(wh.object_at(&Point::new(2, 0)), None);
+    wh.set_at(&Point::new(3, 1), Tile::Wall);
+    assert_eq!(wh.robot(), None);
+    assert_eq!(wh.apply(Direction::North), None);
+    // Off the map, nothing happens
+    wh.set_at(&Point::new(4, 0), Tile::Wall);
+    assert_eq!(rows(&wh), vec![".O..", "...#"]);
+}
+
+#[test]
+fn test_apply() {
+    let mut wh = warehouse(&["#.@OO.#"]);
+    assert_eq!(wh.apply(Direction::East), Some(MoveOutcome::Pushed(2)));
+    assert_eq!(wh.apply(Direction::East), Some(MoveOutcome::Blocked(Point::new(6, 0))));
+    assert_eq!(rows(&wh), vec!["#..@OO#"]);
+    assert_eq!(wh.apply(Direction::West), Some(MoveOutcome::Moved));
+    assert_eq!(wh.robot(), Some(Point::new(2, 0)));
+    assert_eq!(wh.gps_sum(), 4 + 5);
+    assert_eq!(wh.widen().gps_sum(), 8 + 10);
+}
+
+#[test]
+fn test_move_outcome_display() {
+    assert_eq!(MoveOutcome::Moved.to_string(), "moved");
+    assert_eq!(MoveOutcome::Pushed(1).to_string(), "pushed 1 object");
+    assert_eq!(MoveOutcome::Pushed(3).to_string(), "pushed 3 objects");
+    assert_eq!(MoveOutcome::Blocked(Point::new(6, -1)).to_string(), "blocked at (6,-1)");
+}
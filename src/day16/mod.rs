@@ -1,10 +1,12 @@
-use std::collections::HashSet;
-use nom::combinator::map;
 use nom::Parser;
+use crate::day16::search::{Costs, OrientedSearch};
 use crate::utils::read_input_file;
-use pathfinding::prelude::{astar, astar_bag};
+
+pub mod search;
 
 const DAY: &str = "day16";
+/// Reindeer can't turn around in one go
+const REINDEER_COSTS: Costs = Costs { forward: 1, turn: 1000, u_turn: None };
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 enum Tile {
@@ -23,7 +25,7 @@ impl TryFrom<char> for Tile {
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
-enum Dir {
+pub enum Dir {
     North, East, South, West,
 }
 impl Dir {
@@ -46,9 +48,9 @@ impl Dir {
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
-struct Pos {
-    x: i32,
-    y: i32,
+pub struct Pos {
+    pub x: i32,
+    pub y: i32,
 }
 
 impl Pos {
@@ -71,7 +73,11 @@ impl<T: Copy> Map<T> {
         self.data.len() / self.width
     }
     fn contains_pos(&self, pos: &Pos) -> bool {
-        0 < pos.x && pos.x < (self.width as i32) && 0 < pos.y && pos.y < (self.height() as i32)
+        0 <= pos.x && pos.x < (self.width as i32) && 0 <= pos.y && pos.y < (self.height() as i32)
+    }
+    /// None for positions off the map
+    fn get(&self, pos: &Pos) -> Option<T> {
+        self.contains_pos(pos).then(|| self.get_at(pos))
     }
     fn get_at(&self, pos: &Pos) -> T {
         self.data[pos.y as usize * self.width + pos.x as usize]
@@ -128,103 +134,88 @@ fn parse(str: &str) -> Result<Maze, &str> {
     }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
-struct Reindeer {
-    position: Pos,
-    direction: Dir,
-}
-impl Reindeer {
-    const AVAILABLE_MOVES: [Cmd;3] = [Cmd::Fwd, Cmd::TurnRight, Cmd::TurnLeft];
-    fn apply(&self, cmd: &Cmd) -> Reindeer {
-        match cmd {
-            Cmd::Fwd => Reindeer {
-                position: self.position.step(&self.direction),
-                direction: self.direction,
-            },
-            Cmd::TurnRight => Reindeer {
-                position: self.position,
-                direction: self.direction.right(),
-            },
-            Cmd::TurnLeft => Reindeer {
-                position: self.position,
-                direction: self.direction.left(),
-            }
-        }
-    }
-}
-
-enum Cmd { Fwd, TurnRight, TurnLeft }
-type Path = Vec<Cmd>;
-trait Cost {
-    fn cost(&self) -> usize;
-}
-impl Cost for Cmd {
-    fn cost(&self) -> usize {
-        match self {
-            Cmd::Fwd => 1,
-            Cmd::TurnRight | Cmd::TurnLeft => 1000,
-        }
-    }
-}
-impl Cost for Path {
-    fn cost(&self) -> usize {
-        self.iter().map(Cmd::cost).sum()
+impl Maze {
+    /// The reindeer starts facing east and may end facing any way
+    fn search(&self) -> OrientedSearch<impl Fn(&Pos) -> bool + '_> {
+        OrientedSearch::new(|pos: &Pos| self.map.get(pos) == Some(Tile::Empty), self.start, self.end)
+            .with_costs(REINDEER_COSTS)
+            .with_start_directions(vec![Dir::East])
     }
 }
 
-fn get_available_moves(maze: &Map<Tile>, pos: Pos, dir: Dir) -> Vec<Cmd> {
-    Reindeer::AVAILABLE_MOVES.into_iter().filter(|mv| {
-        match mv {
-            Cmd::TurnRight| Cmd::TurnLeft => true,
-            Cmd::Fwd => maze.get_at(&pos.step(&dir)) == Tile::Empty,
-        }
-    }).collect()
+fn solve_simple(maze: &Maze) -> Result<usize, &str> {
+    maze.search().best_path().map(|(_, cost)| cost).ok_or("No solution found")
 }
+fn solve_advanced(maze: &Maze) -> Result<usize, &str> {
+    maze.search().best_tiles().map(|tiles| tiles.len()).ok_or("No solution found")
+}
+
+#[cfg(test)]
+const DEMO1: &str = "###############
+#.......#....E#
+#.#.###.#.###.#
+#.....#.#...#.#
+#.###.#####.#.#
+#.#.#.......#.#
+#.#.#####.###.#
+#...........#.#
+###.#.#####.#.#
+#...#.....#.#.#
+#.#.#.###.#.#.#
+#.....#...#.#.#
+#.###.#.#.#.#.#
+#S..#.....#...#
+###############";
+#[cfg(test)]
+const DEMO2: &str = "#################
+#...#...#...#..E#
+#.#.#.#.#.#.#.#.#
+#.#.#.#...#...#.#
+#.#.#.#.###.#.#.#
+#...#.#.#.....#.#
+#.#.#.#.#.#####.#
+#.#...#.#.#.....#
+#.#.#####.#.###.#
+#.#.#.......#...#
+#.#.###.#####.###
+#.#.#...#.....#.#
+#.#.#.#####.###.#
+#.#.#.........#.#
+#.#.#.#########.#
+#S#.............#
+#################";
 
-fn min_bound_cost(from: Pos, to:Pos) -> usize {
-    let mut total = 0;
-    let x_diff = from.x.abs_diff(to.x) as usize;
-    let y_diff = from.y.abs_diff(to.y) as usize;
-    total += (x_diff + y_diff) * Cmd::Fwd.cost();
-    if x_diff > 0 && y_diff > 0 {
-        total += Cmd::TurnRight.cost();
-    }
-    return total;
+#[test]
+fn test_contains_pos() {
+    let maze = parse("S.\n.E").unwrap();
+    assert!(maze.map.contains_pos(&Pos { x: 0, y: 0 }));
+    assert!(maze.map.contains_pos(&Pos { x: 1, y: 1 }));
+    assert!(!maze.map.contains_pos(&Pos { x: -1, y: 0 }));
+    assert!(!maze.map.contains_pos(&Pos { x: 2, y: 0 }));
+    assert!(!maze.map.contains_pos(&Pos { x: 0, y: 2 }));
+    assert_eq!(maze.map.get(&Pos { x: 0, y: -1 }), None);
 }
 
-fn solve_simple(maze: &Maze) -> Result<usize, &str> {
-    let result = astar(
-        &Reindeer { position: maze.start, direction: Dir::East },
-        |&r| -> Vec<(Reindeer, usize)> {
-            get_available_moves(&maze.map, r.position, r.direction).iter().map(|m| (r.apply(m), m.cost())).collect()
-        },
-        |&r| min_bound_cost(r.position, maze.end),
-        |state: &Reindeer| state.position == maze.end,
-    );
-
-    if let Some(result) = result {
-        Ok(result.1)
-    } else {
-        Err("No solution found")
-    }
+#[test]
+fn test_no_outer_wall() {
+    // The reindeer looks past the edge of the map right at the start
+    let maze = parse("S..\n.#.\n..E").unwrap();
+    assert_eq!(solve_simple(&maze), Ok(1004));
+    assert_eq!(solve_advanced(&maze), Ok(5));
 }
-fn solve_advanced(maze: &Maze) -> Result<usize, &str> {
-    let result = astar_bag(
-        &Reindeer { position: maze.start, direction: Dir::East },
-        |&r| -> Vec<(Reindeer, usize)> {
-            get_available_moves(&maze.map, r.position, r.direction).iter().map(|m| (r.apply(m), m.cost())).collect()
-        },
-        |&r| min_bound_cost(r.position, maze.end),
-        |state: &Reindeer| state.position == maze.end,
-    );
 
-    if let Some(result) = result {
-        let paths = result.0;
-        let positions: HashSet<Pos> = paths.flat_map(|path| path.iter().map(|n: &Reindeer| n.position).collect::<Vec<Pos>>()).collect();
-        Ok(positions.len())
-    } else {
-        Err("No solution found")
-    }
+#[test]
+fn test_demos() {
+    let demo1 = parse(DEMO1).expect("Failed to parse demo 1");
+    let demo2 = parse(DEMO2).expect("Failed to parse demo 2");
+    assert_eq!(solve_simple(&demo1), Ok(7036));
+    assert_eq!(solve_simple(&demo2), Ok(11048));
+    assert_eq!(solve_advanced(&demo1), Ok(45));
+    assert_eq!(solve_advanced(&demo2), Ok(64));
+    // The reindeer turns at least once on the way, and there are several ways to go
+    let (paths, cost) = demo1.search().all_best_paths().unwrap();
+    assert_eq!(cost, 7036);
+    assert!(paths.len() > 1);
 }
 
 #[test]
@@ -0,0 +1,368 @@
+use std::collections::HashSet;
+use pathfinding::prelude::{astar, astar_bag, dijkstra_all};
+use crate::day16::{Dir, Pos};
+
+const ALL_DIRECTIONS: [Dir; 4] = [Dir::North, Dir::East, Dir::South, Dir::West];
+
+/// What each move of the agent costs
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Costs {
+    /// One step in the direction the agent is facing
+    pub forward: usize,
+    /// Turning 90 degrees either way, without moving
+    pub turn: usize,
+    /// Turning around in one go, or None if that takes two turns
+    pub u_turn: Option<usize>,
+}
+impl Default for Costs {
+    fn default() -> Self {
+        Costs { forward: 1, turn: 1, u_turn: None }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Agent {
+    pub position: Pos,
+    pub direction: Dir,
+}
+
+/// Cheapest ways for an agent that faces one of four directions to get from start to goal on a
+/// grid, where turning has a cost of its own
+pub struct OrientedSearch<F: Fn(&Pos) -> bool> {
+    /// Whether the agent can stand on a position. Also asked about positions off the grid
+    passable: F,
+    start: Pos,
+    goal: Pos,
+    costs: Costs,
+    /// Directions the agent can be facing at the start, for free
+    start_directions: Vec<Dir>,
+    /// Directions the agent has to be facing at the goal, None for any
+    goal_directions: Option<Vec<Dir>>,
+}
+
+impl<F: Fn(&Pos) -> bool> OrientedSearch<F> {
+    /// Starts facing any direction, ends facing any direction, with [Costs::default]
+    pub fn new(passable: F, start: Pos, goal: Pos) -> OrientedSearch<F> {
+        OrientedSearch {
+            passable,
+            start,
+            goal,
+            costs: Costs::default(),
+            start_directions: ALL_DIRECTIONS.to_vec(),
+            goal_directions: None,
+        }
+    }
+    pub fn with_costs(self, costs: Costs) -> OrientedSearch<F> {
+        OrientedSearch { costs, ..self }
+    }
+    pub fn with_start_directions(self, start_directions: Vec<Dir>) -> OrientedSearch<F> {
+        OrientedSearch { start_directions, ..self }
+    }
+    pub fn with_goal_directions(self, goal_directions: Vec<Dir>) -> OrientedSearch<F> {
+        OrientedSearch { goal_directions: Some(goal_directions), ..self }
+    }
+
+    /// The cheapest path with its cost, from the starting agent up to and including the agent at the goal
+    pub fn best_path(&self) -> Option<(Vec<Agent>, usize)> {
+        let (path, cost) = astar(&None, |state| self.successors(state), |state| self.heuristic(state), |state| self.is_goal(state))?;
+        Some((path.into_iter().flatten().collect(), cost))
+    }
+
+    /// Every path that is as cheap as the best one, with that cost
+    pub fn all_best_paths(&self) -> Option<(Vec<Vec<Agent>>, usize)> {
+        let (paths, cost) = astar_bag(&None, |state| self.successors(state), |state| self.heuristic(state), |state| self.is_goal(state))?;
+        Some((paths.map(|path| path.into_iter().flatten().collect()).collect(), cost))
+    }
+
+    /// Every position that is on at least one of the best paths.
+    ///
+    /// There can be exponentially many best paths, so rather than listing them this finds the
+    /// cheapest cost from the start to every agent, and from every agent to the goal. An agent is
+    /// on a best path exactly when the two add up to the best cost
+    pub fn best_tiles(&self) -> Option<HashSet<Pos>> {
+        let from_start = dijkstra_all(&None, |state| self.successors(state));
+        let to_goal = dijkstra_all(&None, |state| self.predecessors(state));
+        let best = from_start.iter()
+            .filter(|(state, _)| self.is_goal(state))
+            .map(|(_, (_, cost))| *cost)
+            .min()?;
+        Some(from_start.iter()
+            .filter(|(state, (_, cost))| to_goal.get(state).is_some_and(|(_, remaining)| cost + remaining == best))
+            .filter_map(|(state, _)| state.map(|agent| agent.position))
+            .collect())
+    }
+
+    /// The search starts from None, which can become any of the starting agents for free
+    fn successors(&self, state: &Option<Agent>) -> Vec<(Option<Agent>, usize)> {
+        let Some(agent) = state else {
+            return self.start_directions.iter()
+                .map(|direction| (Some(Agent { position: self.start, direction: *direction }), 0))
+                .collect();
+        };
+        let mut moves = vec![
+            (Agent { position: agent.position, direction: agent.direction.right() }, self.costs.turn),
+            (Agent { position: agent.position, direction: agent.direction.left() }, self.costs.turn),
+        ];
+        if let Some(u_turn) = self.costs.u_turn {
+            moves.push((Agent { position: agent.position, direction: agent.direction.right().right() }, u_turn));
+        }
+        let ahead = agent.position.step(&agent.direction);
+        if (self.passable)(&ahead) {
+            moves.push((Agent { position: ahead, direction: agent.direction }, self.costs.forward));
+        }
+        moves.into_iter().map(|(agent, cost)| (Some(agent), cost)).collect()
+    }
+
+    /// [OrientedSearch::successors] run backwards, starting from None which can become any of the
+    /// agents at the goal for free
+    fn predecessors(&self, state: &Option<Agent>) -> Vec<(Option<Agent>, usize)> {
+        let Some(agent) = state else {
+            return self.goal_directions.as_deref().unwrap_or(&ALL_DIRECTIONS).iter()
+                .map(|direction| (Some(Agent { position: self.goal, direction: *direction }), 0))
+                .collect();
+        };
+        // Turning is the same both ways round
+        let mut moves = vec![
+            (Agent { position: agent.position, direction: agent.direction.right() }, self.costs.turn),
+            (Agent { position: agent.position, direction: agent.direction.left() }, self.costs.turn),
+        ];
+        if let Some(u_turn) = self.costs.u_turn {
+            moves.push((Agent { position: agent.position, direction: agent.direction.right().right() }, u_turn));
+        }
+        // Stepping forward onto this position, from wherever the agent could have stood before
+        let behind = agent.position.step(&agent.direction.right().right());
+        if (self.passable)(&agent.position) && ((self.passable)(&behind) || behind == self.start) {
+            moves.push((Agent { position: behind, direction: agent.direction }, self.costs.forward));
+        }
+        moves.into_iter().map(|(agent, cost)| (Some(agent), cost)).collect()
+    }
+
+    /// Never more than what's left to pay. Every step still needed, plus the turns to face each
+    /// direction the agent still has to move in, in the cheaper order, and then a direction it
+    /// may end in
+    fn heuristic(&self, state: &Option<Agent>) -> usize {
+        let Some(agent) = state else { return 0 };
+        let Pos { x: dx, y: dy } = Pos { x: self.goal.x - agent.position.x, y: self.goal.y - agent.position.y };
+        let horizontal = match dx.signum() { 1 => Some(Dir::East), -1 => Some(Dir::West), _ => None };
+        let vertical = match dy.signum() { 1 => Some(Dir::South), -1 => Some(Dir::North), _ => None };
+        let steps = (dx.unsigned_abs() + dy.unsigned_abs()) as usize * self.costs.forward;
+
+        let finish = |from: Dir| {
+            self.goal_directions.as_ref()
+                .and_then(|dirs| dirs.iter().map(|to| self.turn_cost(from, *to)).min())
+                .unwrap_or(0)
+        };
+        let turns = match (horizontal, vertical) {
+            (None, None) => finish(agent.direction),
+            (Some(only), None) | (None, Some(only)) => self.turn_cost(agent.direction, only) + finish(only),
+            (Some(h), Some(v)) => {
+                let h_first = self.turn_cost(agent.direction, h) + self.costs.turn + finish(v);
+                let v_first = self.turn_cost(agent.direction, v) + self.costs.turn + finish(h);
+                h_first.min(v_first)
+            }
+        };
+        steps + turns
+    }
+
+    /// The cheapest way to go from facing one direction to facing another, without moving
+    fn turn_cost(&self, from: Dir, to: Dir) -> usize {
+        if from == to {
+            0
+        } else if from.right().right() == to {
+            self.costs.u_turn.map_or(2 * self.costs.turn, |u_turn| u_turn.min(2 * self.costs.turn))
+        } else {
+            self.costs.turn
+        }
+    }
+
+    fn is_goal(&self, state: &Option<Agent>) -> bool {
+        state.is_some_and(|agent| {
+            agent.position == self.goal && self.goal_directions.as_ref().is_none_or(|dirs| dirs.contains(&agent.direction))
+        })
+    }
+}
+
+#[cfg(test)]
+fn open_grid(width: i32, height: i32) -> impl Fn(&Pos) -> bool {
+    move |pos: &Pos| 0 <= pos.x && pos.x < width && 0 <= pos.y && pos.y < height
+}
+
+#[test]
+fn test_straight_line() {
+    let search = OrientedSearch::new(open_grid(5, 1), Pos { x: 0, y: 0 }, Pos { x: 4, y: 0 })
+        .with_start_directions(vec![Dir::East]);
+    let (path, cost) = search.best_path().unwrap();
+    assert_eq!(cost, 4);
+    assert_eq!(path.len(), 5);
+    assert_eq!(path[0], Agent { position: Pos { x: 0, y: 0 }, direction: Dir::East });
+    assert_eq!(path[4], Agent { position: Pos { x: 4, y: 0 }, direction: Dir::East });
+}
+
+#[test]
+fn test_turn_costs() {
+    let start = Pos { x: 2, y: 0 };
+    let goal = Pos { x: 0, y: 0 };
+    // Facing away from the goal, turning around takes two turns
+    let costs = Costs { forward: 1, turn: 10, u_turn: None };
+    let search = OrientedSearch::new(open_grid(3, 1), start, goal).with_costs(costs).with_start_directions(vec![Dir::East]);
+    assert_eq!(search.best_path().unwrap().1, 22);
+    // Unless there is a cheaper u-turn
+    let search = search.with_costs(Costs { u_turn: Some(5), ..costs });
+    assert_eq!(search.best_path().unwrap().1, 7);
+    // Or the agent may start facing any way
+    let search = OrientedSearch::new(open_grid(3, 1), start, goal).with_costs(costs);
+    assert_eq!(search.best_path().unwrap().1, 2);
+    // Having to arrive facing north adds a turn at the end
+    let search = search.with_goal_directions(vec![Dir::North]);
+    let (path, cost) = search.best_path().unwrap();
+    assert_eq!(cost, 12);
+    assert_eq!(path.last().unwrap().direction, Dir::North);
+}
+
+#[test]
+fn test_all_best_paths() {
+    // Around a single wall in the middle of a 3x3 room, both ways are as cheap
+    let passable = |pos: &Pos| open_grid(3, 3)(pos) && *pos != Pos { x: 1, y: 1 };
+    let search = OrientedSearch::new(passable, Pos { x: 0, y: 1 }, Pos { x: 2, y: 1 })
+        .with_costs(Costs { forward: 1, turn: 100, u_turn: None })
+        .with_start_directions(vec![Dir::East]);
+    let (paths, cost) = search.all_best_paths().unwrap();
+    assert_eq!(cost, 4 + 3 * 100);
+    assert_eq!(paths.len(), 2);
+    assert!(paths.iter().all(|path| path[0].direction == Dir::East && path.last().unwrap().position == Pos { x: 2, y: 1 }));
+    let tiles = search.best_tiles().unwrap();
+    assert_eq!(tiles.len(), 8);
+    assert!(!tiles.contains(&Pos { x: 1, y: 1 }));
+
+    // With free turns every shortest path across an open room is a best path, about 10^16 of
+    // them here, and every tile is on one
+    let search = OrientedSearch::new(open_grid(30, 30), Pos { x: 0, y: 0 }, Pos { x: 29, y: 29 })
+        .with_costs(Costs { forward: 1, turn: 0, u_turn: None });
+    assert_eq!(search.best_tiles().map(|tiles| tiles.len()), Some(30 * 30));
+
+    // Nothing if the goal can't be reached
+    let walled = |pos: &Pos| open_grid(3, 3)(pos) && pos.x != 1;
+    let search = OrientedSearch::new(walled, Pos { x: 0, y: 0 }, Pos { x: 2, y: 2 });
+    assert!(search.best_path().is_none());
+    assert!(search.best_tiles().is_none());
+}
+
+#[test]
+fn test_heuristic() {
+    let costs = Costs { forward: 1, turn: 1000, u_turn: None };
+    let search = OrientedSearch::new(open_grid(10, 10), Pos { x: 0, y: 9 }, Pos { x: 9, y: 0 }).with_costs(costs);
+    let h = |x, y, direction| search.heuristic(&Some(Agent { position: Pos { x, y }, direction }));
+    // Facing one of the ways it has to go, it only needs to turn once
+    assert_eq!(h(0, 9, Dir::East), 18 + 1000);
+    assert_eq!(h(0, 9, Dir::North), 18 + 1000);
+    // Facing away, it has to turn twice
+    assert_eq!(h(0, 9, Dir::West), 18 + 2000);
+    assert_eq!(h(0, 0, Dir::West), 9 + 2000);
+    assert_eq!(h(0, 0, Dir::North), 9 + 1000);
+    assert_eq!(h(9, 0, Dir::South), 0);
+    // A cheap u-turn helps when facing away from a straight line
+    let search = search.with_costs(Costs { u_turn: Some(1500), ..costs }).with_goal_directions(vec![Dir::North]);
+    let h = |x, y, direction| search.heuristic(&Some(Agent { position: Pos { x, y }, direction }));
+    assert_eq!(h(0, 0, Dir::West), 9 + 1500 + 1000);
+    assert_eq!(h(9, 0, Dir::South), 1500);
+    assert_eq!(h(9, 0, Dir::East), 1000);
+}
+
+/// Cheapest cost from every agent reachable by `edges` from any of the `sources`, by plain Dijkstra
+#[cfg(test)]
+fn dijkstra_costs(sources: Vec<Agent>, edges: impl Fn(&Agent) -> Vec<(Agent, usize)>) -> std::collections::HashMap<Agent, usize> {
+    use std::cmp::Reverse;
+    use std::collections::{BinaryHeap, HashMap};
+    let mut costs: HashMap<Agent, usize> = HashMap::new();
+    let mut queue: BinaryHeap<Reverse<(usize, i32, i32, u8)>> = BinaryHeap::new();
+    let to_key = |agent: &Agent| (agent.position.x, agent.position.y, ALL_DIRECTIONS.iter().position(|d| *d == agent.direction).unwrap() as u8);
+    for source in sources {
+        costs.insert(source, 0);
+        let (x, y, d) = to_key(&source);
+        queue.push(Reverse((0, x, y, d)));
+    }
+    while let Some(Reverse((cost, x, y, d))) = queue.pop() {
+        let agent = Agent { position: Pos { x, y }, direction: ALL_DIRECTIONS[d as usize] };
+        if costs[&agent] < cost {
+            continue;
+        }
+        for (next, step) in edges(&agent) {
+            if costs.get(&next).is_none_or(|known| cost + step < *known) {
+                costs.insert(next, cost + step);
+                let (x, y, d) = to_key(&next);
+                queue.push(Reverse((cost + step, x, y, d)));
+            }
+        }
+    }
+    costs
+}
+
+#[test]
+fn test_against_dijkstra() {
+    let mut rng = crate::utils::TestRng::new(0x9e3779b97f4a7c15);
+    let mut random = |n: u64| rng.below(n);
+    for round in 0..150 {
+        let (width, height) = (4 + random(9) as i32, 4 + random(9) as i32);
+        let walls: HashSet<Pos> = (0..width * height)
+            .map(|i| Pos { x: i % width, y: i / width })
+            .filter(|_| random(100) < 30)
+            .collect();
+        let open: Vec<Pos> = (0..width * height).map(|i| Pos { x: i % width, y: i / width }).filter(|p| !walls.contains(p)).collect();
+        if open.len() < 2 {
+            continue;
+        }
+        let start = open[random(open.len() as u64) as usize];
+        let goal = open[random(open.len() as u64) as usize];
+        let costs = Costs {
+            forward: 1 + random(5) as usize,
+            turn: 1 + random(30) as usize,
+            u_turn: if random(2) == 0 { None } else { Some(1 + random(60) as usize) },
+        };
+        let some_directions = |bits: u64| ALL_DIRECTIONS.iter().enumerate().filter(|(i, _)| bits & (1 << i) != 0).map(|(_, d)| *d).collect::<Vec<Dir>>();
+        let start_directions = some_directions(1 + random(15));
+        let goal_directions = some_directions(random(16));
+
+        let passable = |pos: &Pos| 0 <= pos.x && pos.x < width && 0 <= pos.y && pos.y < height && !walls.contains(pos);
+        let mut search = OrientedSearch::new(passable, start, goal).with_costs(costs).with_start_directions(start_directions.clone());
+        if !goal_directions.is_empty() {
+            search = search.with_goal_directions(goal_directions.clone());
+        }
+        let goal_agents: Vec<Agent> = (if goal_directions.is_empty() { ALL_DIRECTIONS.to_vec() } else { goal_directions })
+            .into_iter().map(|direction| Agent { position: goal, direction }).collect();
+
+        let from_start = dijkstra_costs(
+            start_directions.iter().map(|direction| Agent { position: start, direction: *direction }).collect(),
+            |agent| search.successors(&Some(*agent)).into_iter().map(|(next, cost)| (next.unwrap(), cost)).collect(),
+        );
+        // Turning is the same both ways round, stepping forward is undone by stepping backward
+        let to_goal = dijkstra_costs(goal_agents.clone(), |agent| {
+            let behind = agent.position.step(&agent.direction.right().right());
+            search.successors(&Some(*agent)).into_iter()
+                .map(|(next, cost)| (next.unwrap(), cost))
+                .filter(|(next, _)| next.position == agent.position)
+                .chain(passable(&behind).then_some((Agent { position: behind, direction: agent.direction }, costs.forward)))
+                .collect()
+        });
+        let best = goal_agents.iter().filter_map(|agent| from_start.get(agent)).min().copied();
+
+        // The heuristic never overestimates, from anywhere
+        for (agent, remaining) in &to_goal {
+            assert!(search.heuristic(&Some(*agent)) <= *remaining, "Round {round}: {agent:?}");
+        }
+        let found = search.best_path();
+        assert_eq!(found.as_ref().map(|(_, cost)| *cost), best, "Round {round}");
+        let Some(best) = best else {
+            assert!(search.best_tiles().is_none());
+            continue;
+        };
+        let (path, _) = found.unwrap();
+        assert_eq!(path.first().unwrap().position, start);
+        assert!(goal_agents.contains(path.last().unwrap()));
+
+        let expected_tiles: HashSet<Pos> = from_start.iter()
+            .filter(|(agent, cost)| to_goal.get(agent).is_some_and(|remaining| **cost + remaining == best))
+            .map(|(agent, _)| agent.position)
+            .collect();
+        assert_eq!(search.best_tiles(), Some(expected_tiles), "Round {round}");
+    }
+}
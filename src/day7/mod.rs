@@ -4,13 +4,21 @@ use std::fmt::{Debug, Display, Formatter};
 use nom::{IResult, Parser};
 
 type Input = Vec<EquationLine>;
+
+/// The integer type equations are evaluated in.
+/// Enable the `day7-u128` feature for inputs whose values don't fit in 64 bits
+#[cfg(not(feature = "day7-u128"))]
+pub type Num = usize;
+#[cfg(feature = "day7-u128")]
+pub type Num = u128;
+
 #[derive(Debug)]
 struct EquationLine {
-    expected: usize,
+    expected: Num,
     equation: Equation
 }
-impl From<(usize, Equation)> for EquationLine {
-    fn from((expected, equation): (usize, Equation)) -> Self {
+impl From<(Num, Equation)> for EquationLine {
+    fn from((expected, equation): (Num, Equation)) -> Self {
         Self { expected, equation }
     }
 }
@@ -21,10 +29,10 @@ impl Display for EquationLine {
 }
 #[derive(Debug)]
 struct Equation {
-    values: Vec<usize>,
+    values: Vec<Num>,
 }
-impl From<Vec<usize>> for Equation {
-    fn from(values: Vec<usize>) -> Self {
+impl From<Vec<Num>> for Equation {
+    fn from(values: Vec<Num>) -> Self {
         Equation { values }
     }
 }
@@ -34,20 +42,22 @@ impl Display for Equation {
     }
 }
 
-fn solve(input: &Input, operators: &[Op]) -> usize {
-    let mut total = 0;
+/// The sum of the expected values of every line that some choice of operators makes true,
+/// or None if that sum doesn't fit in [Num]
+fn solve(input: &Input, operators: &[Op]) -> Option<Num> {
+    let mut total: Num = 0;
     for line in input {
         let mut potential_values = HashSet::from([0]);
         for value in line.equation.values.iter() {
             potential_values = potential_values.iter().flat_map(|v|
-                operators.iter().map(|op| op(v, value))
+                operators.iter().filter_map(|op| op(v, value))
             ).filter(|v| *v <= line.expected).collect();
         }
         if potential_values.contains(&line.expected) {
-            total += line.expected;
+            total = total.checked_add(line.expected)?;
         }
     }
-    return total;
+    Some(total)
 }
 
 // region input
@@ -63,7 +73,7 @@ fn parse_input(data: &str) -> Result<Vec<EquationLine>, ()> {
         ParseError,
     }
 
-    fn number(s: &str) -> IResult<&str, usize> {
+    fn number(s: &str) -> IResult<&str, Num> {
         digit1.map_res(str::parse).parse(s)
     }
     fn equation(s: &str) -> IResult<&str, Equation> {
@@ -82,10 +92,18 @@ fn parse_input(data: &str) -> Result<Vec<EquationLine>, ()> {
 // endregion
 
 
-type Op = fn(a: &usize, b: &usize) -> usize;
-const OP_ADD: Op = |a:&usize, b: &usize| a+b;
-const OP_MUL: Op = |a:&usize, b: &usize| a*b;
-const OP_CON: Op = |a:&usize, b: &usize| (a.to_string() + &*b.to_string()).parse::<usize>().unwrap();
+/// Combines two values, or returns None if the result doesn't fit in [Num].
+/// Overflowing branches can never reach the expected value, so they are simply discarded
+type Op = fn(a: &Num, b: &Num) -> Option<Num>;
+const OP_ADD: Op = |a:&Num, b: &Num| a.checked_add(*b);
+const OP_MUL: Op = |a:&Num, b: &Num| a.checked_mul(*b);
+const OP_CON: Op = |a:&Num, b: &Num| {
+    let mut shift: Num = 10;
+    while shift <= *b {
+        shift = shift.checked_mul(10)?;
+    }
+    a.checked_mul(shift)?.checked_add(*b)
+};
 
 const SIMPLE_OP: [Op; 2] = [OP_ADD, OP_MUL];
 const ADV_OP: [Op; 3] = [OP_ADD, OP_MUL, OP_CON];
@@ -94,22 +112,64 @@ const ADV_OP: [Op; 3] = [OP_ADD, OP_MUL, OP_CON];
 fn test_solve_simple() {
     let demo = read_input_file("day7", "demo.txt");
     let state = parse_input(&demo).expect("Demo file should parse");
-    assert_eq!(solve(&state, &SIMPLE_OP), 3749);
+    assert_eq!(solve(&state, &SIMPLE_OP), Some(3749));
 }
 #[test]
 fn test_solve_advanced() {
     let demo = read_input_file("day7", "demo.txt");
     let state = parse_input(&demo).expect("Demo file should parse");
-    assert_eq!(solve(&state, &ADV_OP), 11387);
+    assert_eq!(solve(&state, &ADV_OP), Some(11387));
+}
+#[test]
+fn test_concatenation() {
+    assert_eq!(OP_CON(&12, &345), Some(12345));
+    assert_eq!(OP_CON(&7, &0), Some(70));
+    assert_eq!(OP_CON(&7, &10), Some(710));
+    assert_eq!(OP_CON(&0, &9), Some(9));
+}
+#[test]
+fn test_overflowing_branches_are_discarded() {
+    let max = Num::MAX;
+    assert_eq!(OP_ADD(&max, &1), None);
+    assert_eq!(OP_MUL(&max, &2), None);
+    assert_eq!(OP_CON(&max, &1), None);
+    assert_eq!(OP_CON(&1, &max), None);
+
+    // 9999999999 || 9999999999 is about 2^66, which used to panic in parse::<usize>()
+    // The overflowing branch is dropped, the additive one still matches
+    let state = parse_input("19999999998: 9999999999 9999999999").expect("Line should parse");
+    assert_eq!(solve(&state, &ADV_OP), Some(19999999998));
+}
+#[test]
+#[cfg(not(feature = "day7-u128"))]
+fn test_largest_concatenation_that_fits() {
+    let state = parse_input("18446744073709551615: 1844674407370955161 5").expect("Line should parse");
+    assert_eq!(solve(&state, &ADV_OP), Some(18446744073709551615));
+}
+#[test]
+fn test_total_overflow() {
+    let max = Num::MAX;
+    // Each line fits on its own, only their sum doesn't
+    let state = parse_input(&format!("{max}: {max}\n1: 1")).expect("Lines should parse");
+    assert_eq!(solve(&state, &SIMPLE_OP), None);
+    let state = parse_input(&format!("{max}: {max}\n1: 2")).expect("Lines should parse");
+    assert_eq!(solve(&state, &SIMPLE_OP), Some(max));
+}
+#[test]
+#[cfg(feature = "day7-u128")]
+fn test_concatenation_beyond_64_bits() {
+    let state = parse_input("99999999999999999999: 9999999999 9999999999\n18446744073709551616: 1844674407370955161 6")
+        .expect("Lines should parse");
+    assert_eq!(solve(&state, &ADV_OP), Some(99999999999999999999 + 18446744073709551616));
 }
 
-pub fn part1() -> usize {
+pub fn part1() -> Num {
     let full = read_input_file("day7", "full.txt");
     let state = parse_input(&full).expect("Demo file should parse");
-    solve(&state, &SIMPLE_OP)
+    solve(&state, &SIMPLE_OP).expect("Total should fit, otherwise enable the day7-u128 feature")
 }
-pub fn part2() -> usize {
+pub fn part2() -> Num {
     let full = read_input_file("day7", "full.txt");
     let state = parse_input(&full).expect("Demo file should parse");
-    solve(&state, &ADV_OP)
+    solve(&state, &ADV_OP).expect("Total should fit, otherwise enable the day7-u128 feature")
 }
\ No newline at end of file
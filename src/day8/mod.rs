@@ -1,4 +1,5 @@
-use crate::utils::read_input_file;
+use crate::utils::heatmap::{HeatMap, Style};
+use crate::utils::{gcd, read_input_file};
 use nom::Parser;
 use std::cmp::PartialEq;
 use std::collections::HashMap;
@@ -36,6 +37,16 @@ struct Offset {
     col: isize,
     row: isize,
 }
+impl Offset {
+    /// The smallest step in the same direction that still lands on whole grid positions
+    fn reduced(&self) -> Self {
+        let divisor = gcd(self.col.unsigned_abs(), self.row.unsigned_abs()).max(1) as isize;
+        Offset {
+            col: self.col / divisor,
+            row: self.row / divisor,
+        }
+    }
+}
 #[derive(Eq, PartialEq, Clone, Copy, Debug, Hash)]
 struct Pos {col: isize, row: isize}
 impl Pos {
@@ -73,10 +84,13 @@ struct Map2D<T> where T: Display{
 
 enum Error { OutOfBounds }
 impl<T: Display> Map2D<T> {
+    fn contains(&self, pos: &Pos) -> bool {
+        (0..self.width as isize).contains(&pos.col) && (0..self.height as isize).contains(&pos.row)
+    }
     /// Returns the given position as a map index, ONLY IF the position is within the bounds of the map
     fn as_index(&self, pos: &Pos) -> Option<usize> {
-        if 0 <= pos.col && pos.col <= self.width as isize &&
-        0 <= pos.row && pos.row <= self.height as isize {
+        if 0 <= pos.col && pos.col < self.width as isize &&
+        0 <= pos.row && pos.row < self.height as isize {
             Some((pos.col + (self.width as isize * pos.row)) as usize)
         } else {
             None
@@ -105,7 +119,7 @@ impl<T: Display> Map2D<T> {
         self.map[index] = cell;
         Ok(())
     }
-    fn update_cell(&mut self, pos: &Pos, update: fn(&T) -> T) -> Result<(), ()> {
+    fn update_cell(&mut self, pos: &Pos, update: impl FnOnce(&T) -> T) -> Result<(), ()> {
         let prev = self.get_cell(pos).ok_or(())?;
         self.set_cell(pos, update(prev))
     }
@@ -127,13 +141,15 @@ impl<T: Display> Display for Map2D<T> {
 
 // region input
 fn parse_file(fname: &str) -> Result<Map2D<Cell>, ()> {
-    let data = read_input_file("day8", fname);
+    parse_input(&read_input_file("day8", fname))
+}
+fn parse_input(data: &str) -> Result<Map2D<Cell>, ()> {
     let (first, _) = data.split_once("\n").ok_or(()).expect("Map should have at least two lines to determine width");
     let width: usize = first.len();
     Ok(Map2D {
         map: data.chars().filter(|c| !c.is_whitespace()).map(Cell::try_from).collect::<Result<Vec<Cell>, _>>()?,
         width: width,
-        height: data.len() / width,
+        height: data.lines().count(),
     })
 }
 // endregion
@@ -141,103 +157,82 @@ fn parse_file(fname: &str) -> Result<Map2D<Cell>, ()> {
 type AntennaMap = Map2D<Cell>;
 type AntinodesMap = Map2D<usize>;
 
-fn print_numeric(map: &AntinodesMap) {
-    for y in 0..map.height {
-        for x in 0..map.width {
-            let value = map.map[x + map.width * y];
-            print!("{}", match value {
-                0 => '.',
-                1 => '1',
-                2 => '2',
-                3 => '3',
-                4 => '4',
-                5 => '5',
-                6 => '6',
-                7 => '7',
-                8 => '8',
-                9 => '9',
-                _ => '#',
-            });
-        }
-        println!();
-    }
+#[derive(Clone, Copy, Debug)]
+pub enum Resonance {
+    /// One antinode on the far side of each antenna, at the same distance as the pair
+    Simple,
+    /// Antinodes at every grid position in line with the pair, including the antennas themselves
+    Harmonic,
 }
 
-fn solve_simple(initial_state: &AntennaMap) -> usize {
+fn antennas_by_frequency(map: &AntennaMap) -> HashMap<Frequency, Vec<Pos>> {
     let mut antenna_locations_by_frequency: HashMap<Frequency, Vec<Pos>> = HashMap::new();
-    // Find and group up antennas by frequency
-    initial_state.enumerate().for_each(|(pos, cell)| {
-         if let Some(antenna) = cell.antenna {
-             if antenna_locations_by_frequency.contains_key(&antenna) {
-                 antenna_locations_by_frequency.get_mut(&antenna).unwrap().push(pos)
-             } else {
-                 antenna_locations_by_frequency.insert(antenna, vec![pos]);
-             }
-         }
+    map.enumerate().for_each(|(pos, cell)| {
+        if let Some(antenna) = cell.antenna {
+            antenna_locations_by_frequency.entry(antenna).or_default().push(pos)
+        }
     });
+    antenna_locations_by_frequency
+}
+
+/// Antinodes produced by the antenna at `from` resonating with the antenna at `to`,
+/// within the bounds of the map. Simple resonance gives the one antinode past `to`, harmonic
+/// resonance gives every grid position from `from` onwards through `to`, the antennas included.
+/// Calling this for both (a, b) and (b, a) covers every antinode of the pair
+fn antinodes<T: Display>(map: &Map2D<T>, from: &Pos, to: &Pos, resonance: Resonance) -> Vec<Pos> {
+    match resonance {
+        Resonance::Simple => {
+            let antinode = to.apply_offset(&from.offset_to(to));
+            map.contains(&antinode).then_some(antinode).into_iter().collect()
+        }
+        Resonance::Harmonic => {
+            let step = from.offset_to(to).reduced();
+            (0..).map(|times| from.apply_offset_times(&step, times))
+                .take_while(|pos| map.contains(pos))
+                .collect()
+        }
+    }
+}
 
+/// How many antenna pairs produce an antinode at each cell
+fn antinode_counts(initial_state: &AntennaMap, resonance: Resonance) -> AntinodesMap {
     let mut antinodes_map = AntinodesMap {
         map: Vec::from_iter(initial_state.map.iter().map(|_| 0)),
         width: initial_state.width,
         height: initial_state.height,
     };
-    for (_, positions) in antenna_locations_by_frequency.iter() {
-        positions.iter().for_each(|pos| {
-            positions.iter().for_each(|pos2| {
+    for (_, positions) in antennas_by_frequency(initial_state).iter() {
+        for pos in positions {
+            for pos2 in positions {
                 if pos == pos2 {
-                    return
+                    continue
                 }
-                let antinode = pos2.apply_offset(&pos.offset_to(&pos2));
-                match antinodes_map.update_cell(&antinode, |c| c+1) {
-                    Ok(_) => {} // Registered new antinode
-                    Err(_) => {} // Antinode is off the map so we don't care
+                for antinode in antinodes(initial_state, pos, pos2, resonance) {
+                    antinodes_map.update_cell(&antinode, |c| c+1).expect("Antinodes are always on the map");
                 }
-            })
-        })
-    };
+            }
+        }
+    }
+    antinodes_map
+}
 
-    let unique_antinode_locations = antinodes_map.enumerate().map(|(p,v)| *v)
-        .filter(|v| v > &0usize)
-        .count();
-    
-    assert_eq!(antinodes_map.map.len(), initial_state.width * initial_state.height);
+fn count_antinode_locations(initial_state: &AntennaMap, resonance: Resonance) -> usize {
+    antinode_counts(initial_state, resonance).map.iter()
+        .filter(|v| **v > 0)
+        .count()
+}
 
-    // for y in 0..initial_state.height {
-    //     for x in 0..initial_state.width {
-    //         let pos = Pos { col: x as isize, row: y as isize };
-    //         let cell = initial_state.get_cell(&pos).unwrap();
-    //         let power = *antinodes_map.get_cell(&pos).unwrap();
-    //         const gray: &str = "\x1b[90m";
-    //         const green: &str = "\x1b[92m";
-    //         const yellow: &str = "\x1b[93m";
-    //         const red: &str = "\x1b[91m";
-    //         const purple: &str = "\x1b[95m";
-    //         const reset: &str = "\x1b[0m";
-    //         match (cell, power) {
-    //             (Cell{antenna: None}, 0) => print!("{}░{} ", gray, reset),
-    //             (Cell{antenna: None}, 1) => print!("{}░{} ", green, reset),
-    //             (Cell{antenna: None}, 2) => print!("{}░{} ", yellow, reset),
-    //             (Cell{antenna: None}, 3) => print!("{}░{} ", red, reset),
-    //             (Cell{antenna: None}, _) => print!("{}░{} ", red, reset),
-    // 
-    //             (Cell{antenna: Some(freq)}, 0) => print!("{freq} "),
-    //             (Cell{antenna: Some(freq)}, 1) => print!("{}{freq}{} ", green, reset),
-    //             (Cell{antenna: Some(freq)}, 2) => print!("{}{freq}{} ", yellow, reset),
-    //             (Cell{antenna: Some(freq)}, 3) => print!("{}{freq}{} ", red, reset),
-    //             (Cell{antenna: Some(freq)}, _) => print!("{}{freq}{} ", purple, reset),
-    // 
-    //         }
-    //     }
-    //     print!("\n")
-    // }
-    // println!("Antennas");
-    // println!("{}", initial_state);
-    // println!("Antinodes");
-    // print_numeric(&antinodes_map);
-    unique_antinode_locations
+fn solve_simple(initial_state: &AntennaMap) -> usize {
+    count_antinode_locations(initial_state, Resonance::Simple)
 }
 fn solve_advanced(initial_state: &AntennaMap) -> usize {
-    0
+    count_antinode_locations(initial_state, Resonance::Harmonic)
+}
+
+/// The antennas, coloured by how many antinodes overlap on each cell
+fn render_antinodes(initial_state: &AntennaMap, resonance: Resonance, style: Style) -> String {
+    let antinodes_map = antinode_counts(initial_state, resonance);
+    HeatMap::new(&initial_state.map, &antinodes_map.map, initial_state.width).render(style)
 }
 
 #[test]
@@ -250,9 +245,179 @@ fn test_solve_simple() {
     let answer = solve_simple(&full_input);
     assert!(answer < 366); // Failed submission 1
 }
+#[cfg(test)]
+const DEMO: &str = "\
+............
+........0...
+.....0......
+.......0....
+....0.......
+......A.....
+............
+............
+........A...
+.........A..
+............
+............";
+
+#[test]
+fn test_solve_simple_demo() {
+    let demo_input = parse_input(DEMO).expect("demo failed to parse");
+    assert_eq!(solve_simple(&demo_input), 14);
+}
 #[test]
 fn test_solve_advanced() {
-    todo!()
+    let demo_input = parse_input(DEMO).expect("demo failed to parse");
+    assert_eq!(solve_advanced(&demo_input), 34);
+
+    let t_demo = parse_input("\
+T.........
+...T......
+.T........
+..........
+..........
+..........
+..........
+..........
+..........
+..........").expect("demo failed to parse");
+    assert_eq!(solve_advanced(&t_demo), 9);
+}
+#[test]
+fn test_harmonics_include_intermediate_lattice_points() {
+    // The pair is 4 columns and 2 rows apart, so the line also crosses (2, 1) and (6, 3)
+    let map = parse_input("\
+a......
+.......
+....a..
+.......
+").expect("map failed to parse");
+    let antennas = antennas_by_frequency(&map)[&'a'].clone();
+    let mut nodes = antinodes(&map, &antennas[0], &antennas[1], Resonance::Harmonic);
+    nodes.extend(antinodes(&map, &antennas[1], &antennas[0], Resonance::Harmonic));
+    nodes.sort_by_key(|p| (p.row, p.col));
+    nodes.dedup();
+    assert_eq!(nodes, vec![
+        Pos { col: 0, row: 0 },
+        Pos { col: 2, row: 1 },
+        Pos { col: 4, row: 2 },
+        Pos { col: 6, row: 3 },
+    ]);
+}
+
+#[test]
+fn test_render_antinodes() {
+    let map = parse_input("a...\n..a.\n....\n").expect("map failed to parse");
+    assert_eq!(render_antinodes(&map, Resonance::Simple, Style::Plain).lines().take(3).collect::<Vec<_>>(), vec![
+        "a...",
+        "..a.",
+        "....",
+    ]);
+    assert_eq!(render_antinodes(&map, Resonance::Harmonic, Style::Plain).lines().take(3).collect::<Vec<_>>(), vec![
+        "2...",
+        "..2.",
+        "....",
+    ]);
+}
+#[test]
+fn test_as_index_bounds() {
+    let map = parse_input("abc\ndef\n").expect("map failed to parse");
+    assert_eq!(map.as_index(&Pos { col: 0, row: 0 }), Some(0));
+    assert_eq!(map.as_index(&Pos { col: 2, row: 1 }), Some(5));
+    // One past the last column used to wrap around to the start of the next row
+    assert_eq!(map.as_index(&Pos { col: 3, row: 0 }), None);
+    // One past the last row used to index past the end of the map
+    assert_eq!(map.as_index(&Pos { col: 0, row: 2 }), None);
+    assert_eq!(map.as_index(&Pos { col: -1, row: 0 }), None);
+    assert_eq!(map.as_index(&Pos { col: 0, row: -1 }), None);
+}
+#[test]
+fn test_update_cell_can_capture_state() {
+    let mut map = AntinodesMap { map: vec![0; 4], width: 2, height: 2 };
+    let step = 5;
+    map.update_cell(&Pos { col: 1, row: 1 }, |c| c + step).expect("Cell is on the map");
+    assert_eq!(map.map, vec![0, 0, 0, 5]);
+    assert!(map.update_cell(&Pos { col: 2, row: 0 }, |c| c + step).is_err());
+}
+#[test]
+fn test_antinodes_on_the_edge_of_the_map() {
+    // Antinodes land exactly on the first and last column
+    let map = parse_input(".aa.\n....\n").expect("map failed to parse");
+    assert_eq!(solve_simple(&map), 2);
+    let map = parse_input(".a.a..\n").expect("map failed to parse");
+    let counts = antinode_counts(&map, Resonance::Simple);
+    assert_eq!(counts.map, vec![0, 0, 0, 0, 0, 1]);
+    let map = parse_input("..a.a.\n").expect("map failed to parse");
+    let counts = antinode_counts(&map, Resonance::Simple);
+    assert_eq!(counts.map, vec![1, 0, 0, 0, 0, 0]);
+
+    // Antinodes land exactly on the first and last row
+    let map = parse_input(".\n.\na\n.\na\n").expect("map failed to parse");
+    let counts = antinode_counts(&map, Resonance::Simple);
+    assert_eq!(counts.map, vec![1, 0, 0, 0, 0]);
+    let map = parse_input("a\n.\na\n.\n.\n").expect("map failed to parse");
+    let counts = antinode_counts(&map, Resonance::Simple);
+    assert_eq!(counts.map, vec![0, 0, 0, 0, 1]);
+}
+#[test]
+fn test_antinodes_just_off_the_map() {
+    // The antinode to the right is one column past the edge, it must not wrap onto the next row
+    let map = parse_input("..a.a\n.....\n").expect("map failed to parse");
+    let counts = antinode_counts(&map, Resonance::Simple);
+    assert_eq!(counts.map, vec![1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    // The antinode below is one row past the bottom, it must not be counted or index past the end
+    let map = parse_input("a.\n..\na.\n..\n").expect("map failed to parse");
+    assert_eq!(solve_simple(&map), 0);
+    // Diagonal pairs going off a corner
+    let map = parse_input("...\n.a.\n..a\n").expect("map failed to parse");
+    let counts = antinode_counts(&map, Resonance::Simple);
+    assert_eq!(counts.map, vec![1, 0, 0, 0, 0, 0, 0, 0, 0]);
+    let map = parse_input("..a\n.a.\n...\n").expect("map failed to parse");
+    let counts = antinode_counts(&map, Resonance::Simple);
+    assert_eq!(counts.map, vec![0, 0, 0, 0, 0, 0, 1, 0, 0]);
+}
+#[test]
+fn test_harmonics_stop_at_the_edge_of_the_map() {
+    let map = parse_input("a.a..\n.....\n").expect("map failed to parse");
+    let counts = antinode_counts(&map, Resonance::Harmonic);
+    // Walking from either antenna covers the whole row up to the edge, but never wraps onto the next one
+    assert_eq!(counts.map, vec![2, 2, 2, 1, 1, 0, 0, 0, 0, 0]);
+}
+
+/// Builds a random antenna map with a fixed seed, using a few frequencies so that pairs are common
+#[cfg(test)]
+fn random_map(seed: u64, width: usize, height: usize) -> AntennaMap {
+    let mut rng = crate::utils::TestRng::new(seed);
+    let map = (0..width * height).map(|_| match rng.below(12) {
+        0 => Cell { antenna: Some('a') },
+        1 => Cell { antenna: Some('B') },
+        2 => Cell { antenna: Some('7') },
+        _ => Cell { antenna: None },
+    }).collect();
+    Map2D { map, width, height }
+}
+
+#[test]
+fn test_antinodes_match_brute_force() {
+    for seed in 0..40 {
+        let map = random_map(seed, 5 + (seed as usize % 9), 4 + (seed as usize % 7));
+        let groups = antennas_by_frequency(&map);
+        let pairs = || groups.values().flat_map(|positions| {
+            positions.iter().flat_map(move |a| positions.iter().filter(move |b| *b != a).map(move |b| (a, b)))
+        });
+        let cells = || map.enumerate().map(|(pos, _)| pos);
+
+        let simple = cells().filter(|p| pairs().any(|(a, b)| {
+            b.col - a.col == p.col - b.col && b.row - a.row == p.row - b.row
+        })).count();
+        let harmonic = cells().filter(|p| pairs().any(|(a, b)| {
+            (b.col - a.col) * (p.row - a.row) == (b.row - a.row) * (p.col - a.col)
+        })).count();
+
+        assert_eq!(solve_simple(&map), simple, "seed {seed}\n{map}");
+        assert_eq!(solve_advanced(&map), harmonic, "seed {seed}\n{map}");
+        assert!(harmonic >= simple);
+    }
 }
 pub fn part1() -> usize {
     let full_input = parse_file("full.txt").expect("full.txt failed to parse");
@@ -261,4 +426,8 @@ pub fn part1() -> usize {
 pub fn part2() -> usize {
     let full_input = parse_file("full.txt").expect("full.txt failed to parse");
     solve_advanced(&full_input)
-}
\ No newline at end of file
+}
+pub fn heatmap(resonance: Resonance, style: Style) -> String {
+    let full_input = parse_file("full.txt").expect("full.txt failed to parse");
+    render_antinodes(&full_input, resonance, style)
+}
@@ -1,26 +1,36 @@
 use crate::utils::read_input_file;
 use nom::character::char;
-use std::collections::VecDeque;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fmt::{write, Debug, Display, Formatter};
 
+pub mod strategies;
+
 type Frequency = char;
 type Antenna = Frequency;
 type Strength = usize;
 
-fn char_to_usize(ch: &char) -> usize {
-    match ch {
-        '0' => 0,
-        '1' => 1,
-        '2' => 2,
-        '3' => 3,
-        '4' => 4,
-        '5' => 5,
-        '6' => 6,
-        '7' => 7,
-        '8' => 8,
-        '9' => 9,
-        _ => panic!(),
-    }
+#[derive(Debug, Eq, PartialEq)]
+pub enum ParseError {
+    /// Not a digit in the dense format, or not a pid or `.` in the block format.
+    /// `index` counts characters from the start of the input, including any leading whitespace
+    InvalidCharacter { index: usize, found: String },
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum FormatError {
+    /// The dense format stores lengths as single digits
+    TooLong { pid: usize, len: usize },
+    /// The dense format implies pids from 0 counting up, one per file
+    PidOutOfOrder { expected: usize, found: usize },
+    /// The dense format starts with file 0, so it can't hold free space before it
+    LeadingFreeSpace { len: usize },
+}
+
+fn char_to_usize(index: usize, ch: char) -> Result<usize, ParseError> {
+    ch.to_digit(10)
+        .map(|d| d as usize)
+        .ok_or(ParseError::InvalidCharacter { index, found: ch.to_string() })
 }
 #[derive(Debug, Clone)]
 struct FreeRegion {
@@ -50,28 +60,129 @@ impl Debug for DiskRegion {
 }
 
 #[derive(Debug, Clone)]
-struct DiskMap {
+pub struct DiskMap {
     regions: Vec<DiskRegion>,
 }
 impl DiskMap {
-    fn from_compressed_string(str: &str) -> DiskMap {
-        DiskMap {
+    /// Parses the dense format from the puzzle input, alternating file and free space lengths.
+    /// Surrounding whitespace such as a trailing newline is ignored
+    pub fn from_compressed_string(str: &str) -> Result<DiskMap, ParseError> {
+        let offset = str.chars().take_while(|c| c.is_whitespace()).count();
+        Ok(DiskMap {
             regions: str
+                .trim()
                 .chars()
                 .enumerate()
                 .map(|(i, c)| {
-                    let len = char_to_usize(&c);
+                    let len = char_to_usize(offset + i, c)?;
                     if i % 2 == 0 {
                         let pid = i / 2;
-                        return DiskRegion::Used(UsedRegion { pid, len });
+                        Ok(DiskRegion::Used(UsedRegion { pid, len }))
                     } else {
-                        return DiskRegion::Free(FreeRegion { len });
+                        Ok(DiskRegion::Free(FreeRegion { len }))
                     }
                 })
-                .collect(),
+                .collect::<Result<_, _>>()?,
+        })
+    }
+    /// The inverse of [DiskMap::from_compressed_string].
+    /// Adjacent free regions are merged and adjacent files get an empty free region between them.
+    /// Fails if the disk doesn't start with file 0, since the dense format has no place for that space
+    pub fn to_compressed_string(&self) -> Result<String, FormatError> {
+        let mut out = String::new();
+        // Free space since the last file, None if there wasn't even an empty free region
+        let mut free: Option<usize> = None;
+        let mut expected_pid = 0;
+        let too_long = |pid: usize, len: usize| if len > 9 { Err(FormatError::TooLong { pid, len }) } else { Ok(len) };
+        for region in self.regions.iter() {
+            match region {
+                DiskRegion::Free(FreeRegion { len }) => free = Some(free.unwrap_or(0) + len),
+                DiskRegion::Used(UsedRegion { pid, len }) => {
+                    if *pid != expected_pid {
+                        return Err(FormatError::PidOutOfOrder { expected: expected_pid, found: *pid });
+                    }
+                    if *pid == 0 && free.unwrap_or(0) > 0 {
+                        return Err(FormatError::LeadingFreeSpace { len: free.unwrap_or(0) });
+                    }
+                    if *pid > 0 {
+                        out.push_str(&too_long(*pid, free.unwrap_or(0))?.to_string());
+                    }
+                    out.push_str(&too_long(*pid, *len)?.to_string());
+                    free = None;
+                    expected_pid += 1;
+                }
+            }
         }
+        if let Some(free) = free {
+            if expected_pid == 0 && free > 0 {
+                return Err(FormatError::LeadingFreeSpace { len: free });
+            }
+            out.push_str(&too_long(expected_pid, free)?.to_string());
+        }
+        Ok(out)
+    }
+    /// Parses the expanded format, as produced by [DiskMap::to_block_string].
+    /// Without whitespace every character is one block, otherwise every whitespace separated word is.
+    /// Consecutive blocks with the same pid form one file
+    pub fn from_block_string(str: &str) -> Result<DiskMap, ParseError> {
+        let offset = str.chars().take_while(|c| c.is_whitespace()).count();
+        let str = str.trim();
+        // Every block with the index of its first character
+        let blocks: Vec<(usize, &str)> = if str.contains(char::is_whitespace) {
+            let mut index = offset;
+            str.split(char::is_whitespace)
+                .filter_map(|word| {
+                    let start = index;
+                    index += word.chars().count() + 1;
+                    (!word.is_empty()).then_some((start, word))
+                })
+                .collect()
+        } else {
+            str.char_indices().enumerate().map(|(n, (i, c))| (offset + n, &str[i..i + c.len_utf8()])).collect()
+        };
+        let mut regions: Vec<DiskRegion> = Vec::new();
+        for (index, block) in blocks {
+            let block = if block.chars().all(|c| c == '.') {
+                None
+            } else {
+                Some(block.parse::<usize>().map_err(|_| ParseError::InvalidCharacter { index, found: block.to_string() })?)
+            };
+            match (regions.last_mut(), block) {
+                (Some(DiskRegion::Free(free)), None) => free.len += 1,
+                (Some(DiskRegion::Used(used)), Some(pid)) if used.pid == pid => used.len += 1,
+                (_, None) => regions.push(DiskRegion::Free(FreeRegion { len: 1 })),
+                (_, Some(pid)) => regions.push(DiskRegion::Used(UsedRegion { pid, len: 1 })),
+            }
+        }
+        Ok(DiskMap { regions })
     }
-    fn checksum(&self) -> usize {
+    /// One entry per block, holding the pid of the file in it
+    pub fn to_blocks(&self) -> Vec<Option<usize>> {
+        let mut blocks = Vec::new();
+        for region in self.regions.iter() {
+            match region {
+                DiskRegion::Free(FreeRegion { len }) => blocks.extend(std::iter::repeat_n(None, *len)),
+                DiskRegion::Used(UsedRegion { pid, len }) => blocks.extend(std::iter::repeat_n(Some(*pid), *len)),
+            }
+        }
+        blocks
+    }
+    /// The expanded format from the puzzle statement, e.g. `00...111...2`.
+    /// Once pids no longer fit in one digit, every block is padded to the widest pid and
+    /// separated by a space, e.g. `9 9 . 10 10`
+    pub fn to_block_string(&self) -> String {
+        let blocks = self.to_blocks();
+        let width = blocks.iter().flatten().max().map_or(1, |pid| pid.to_string().len());
+        let separator = if width > 1 { " " } else { "" };
+        blocks.iter()
+            .map(|block| match block {
+                Some(pid) => format!("{pid:>width$}"),
+                None => format!("{:>width$}", "."),
+            })
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+    pub fn checksum(&self) -> usize {
         let mut checksum = 0;
         let mut pos = 0;
         for region in self.regions.iter() {
@@ -90,8 +201,147 @@ impl DiskMap {
         return checksum;
     }
 }
+impl Display for DiskMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_block_string())
+    }
+}
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CompactionMode {
+    /// Single blocks are moved, so files may be split
+    Fragmenting,
+    /// Files are moved as a whole or not at all
+    WholeFile,
+    /// Files are moved as a whole, and no free space is left between them
+    Packed,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum CompactionError {
+    /// A file gained or lost blocks
+    SizeChanged { pid: usize, before: usize, after: usize },
+    /// A file ended up in more than one piece, while compacting whole files
+    FileSplit { pid: usize },
+    /// A file ended up further right than it started, while compacting whole files
+    MovedRight { pid: usize },
+    /// Free space was left before a used block, after fragmenting or packing compaction
+    Gap { pos: usize },
+    /// A block that was moved has a higher pid than one moved before it. Blocks are taken from the
+    /// end of the disk, so moved blocks should appear in decreasing pid order
+    PidOrder { pos: usize },
+}
+
+/// Checks that `after` is a valid compaction of `before`
+pub fn validate_compaction(before: &DiskMap, after: &DiskMap, mode: CompactionMode) -> Result<(), CompactionError> {
+    let before_blocks = before.to_blocks();
+    let after_blocks = after.to_blocks();
+    let max_pid = before_blocks.iter().chain(after_blocks.iter()).flatten().max().map_or(0, |pid| pid + 1);
+    // (size, first block, last block) of every file
+    let extents = |blocks: &[Option<usize>]| {
+        let mut extents = vec![(0, usize::MAX, 0); max_pid];
+        for (pos, pid) in blocks.iter().enumerate() {
+            if let Some(pid) = pid {
+                let (size, first, last) = &mut extents[*pid];
+                *size += 1;
+                *first = (*first).min(pos);
+                *last = pos;
+            }
+        }
+        extents
+    };
+    let before_extents = extents(&before_blocks);
+    let after_extents = extents(&after_blocks);
+
+    for (pid, (before, after)) in before_extents.iter().zip(after_extents.iter()).enumerate() {
+        if before.0 != after.0 {
+            return Err(CompactionError::SizeChanged { pid, before: before.0, after: after.0 });
+        }
+    }
+    if mode != CompactionMode::Fragmenting {
+        for (pid, (before, after)) in before_extents.iter().zip(after_extents.iter()).enumerate() {
+            let (size, first, last) = *after;
+            if size > 0 && last - first + 1 != size {
+                return Err(CompactionError::FileSplit { pid });
+            }
+            if size > 0 && first > before.1 {
+                return Err(CompactionError::MovedRight { pid });
+            }
+        }
+    }
+    if mode != CompactionMode::WholeFile {
+        if let Some(gap) = after_blocks.iter().position(Option::is_none) {
+            if let Some(pos) = after_blocks[gap..].iter().position(Option::is_some) {
+                return Err(CompactionError::Gap { pos: gap + pos });
+            }
+        }
+    }
+    if mode == CompactionMode::Fragmenting {
+        let mut last_moved = usize::MAX;
+        for (pos, block) in after_blocks.iter().enumerate() {
+            if let Some(pid) = block {
+                if before_blocks.get(pos) == Some(block) {
+                    continue;
+                }
+                if *pid > last_moved {
+                    return Err(CompactionError::PidOrder { pos });
+                }
+                last_moved = *pid;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Moves file blocks one at a time from the end of the disk into the leftmost free space,
+/// splitting files wherever needed.
+///
+/// Walks the regions with one cursor from the front and one from the back, so every region is
+/// visited at most once
 fn compress_simple(input: &DiskMap) -> DiskMap {
+    let regions = &input.regions;
+    let mut output: Vec<DiskRegion> = Vec::new();
+    // Index of the file currently being moved from the back, and how many of its blocks are left
+    let mut back = regions.len();
+    let mut back_remaining = 0;
+    let mut front = 0;
+    'front: while front < back {
+        match &regions[front] {
+            DiskRegion::Used(used) => output.push(DiskRegion::Used(used.clone())),
+            DiskRegion::Free(free) => {
+                let mut space = free.len;
+                while space > 0 {
+                    while back_remaining == 0 {
+                        back -= 1;
+                        if back <= front {
+                            break 'front;
+                        }
+                        if let DiskRegion::Used(used) = &regions[back] {
+                            back_remaining = used.len;
+                        }
+                    }
+                    let DiskRegion::Used(used) = &regions[back] else { unreachable!() };
+                    let len = space.min(back_remaining);
+                    output.push(DiskRegion::Used(UsedRegion { pid: used.pid, len }));
+                    space -= len;
+                    back_remaining -= len;
+                }
+            }
+        }
+        front += 1;
+    }
+    if back_remaining > 0 {
+        if let DiskRegion::Used(used) = &regions[back] {
+            output.push(DiskRegion::Used(UsedRegion { pid: used.pid, len: back_remaining }));
+        }
+    }
+    DiskMap { regions: output }
+}
+
+/// The original [compress_simple], kept to check the two-pointer version against
+#[cfg(test)]
+fn compress_simple_reference(input: &DiskMap) -> DiskMap {
+    use std::collections::VecDeque;
     let mut input = VecDeque::from(input.regions.clone());
     let mut output: Vec<UsedRegion> = Vec::new();
     while let Some(ref front) = input.pop_front() {
@@ -136,57 +386,75 @@ fn compress_simple(input: &DiskMap) -> DiskMap {
             .collect(),
     };
 }
-fn fmt_diskregions(d: &Vec<DiskRegion>) -> String {
-    d.iter().map(|r| match r {
-        DiskRegion::Free(FreeRegion{len}) => format!("{}", "_".repeat(*len)),
-        DiskRegion::Used(UsedRegion{len, pid}) => format!("{}", (pid.to_string().repeat(*len)))
-    }).collect()
-}
-fn fmt_vecdeque(d: &VecDeque<DiskRegion>) -> String {
-    d.iter().map(|r| match r {
-        DiskRegion::Free(FreeRegion{len}) => format!("{}", "_".repeat(*len)),
-        DiskRegion::Used(UsedRegion{len, pid}) => format!("{}", (pid.to_string().repeat(*len)))
-    }).collect()
-}
+/// Moves whole files, highest pid first, into the leftmost free span that fits them.
+/// Files that don't fit anywhere to their left stay put.
+///
+/// Free spans are kept in one min-heap per span length, keyed by disk position, so finding the
+/// leftmost span that fits is a peek into at most 9 heaps
 fn compress_advanced(input: &DiskMap) -> DiskMap {
-    let mut input = VecDeque::from(input.regions.clone());
-    let mut output: Vec<DiskRegion> = Vec::new();
-    while let Some(ref front) = input.pop_front() {
-        match front {
-            DiskRegion::Used(ref used) => {
-                output.push(front.clone());
+    // Files are at most 9 blocks long, so any longer span goes in the last heap
+    const MAX_LEN: usize = 9;
+    let mut files: Vec<(usize, UsedRegion)> = Vec::new();
+    // (position, length) of each free span, bucketed by length
+    let mut free_spans: Vec<BinaryHeap<Reverse<(usize, usize)>>> = vec![BinaryHeap::new(); MAX_LEN + 1];
+    let mut add_span = |pos: usize, len: usize| if len > 0 {
+        free_spans[len.min(MAX_LEN)].push(Reverse((pos, len)));
+    };
+    let mut pos = 0;
+    // Free regions only separated by empty files form one span
+    let mut span_start = 0;
+    for region in input.regions.iter() {
+        match region {
+            DiskRegion::Used(used) => {
+                if used.len > 0 {
+                    add_span(span_start, pos - span_start);
+                    span_start = pos + used.len;
+                }
+                files.push((pos, used.clone()));
+                pos += used.len;
             }
-            DiskRegion::Free(ref free) => {
-                let index_to_move = input.iter().enumerate().rfind(|(index, item)| {
-                    if let DiskRegion::Used(used) = item {
-                        if used.len <= free.len {
-                            return true
-                        }
-                    }
-                    return false;
-                });
-                if let Some((index, DiskRegion::Used(used))) = index_to_move {
-                    output.push(DiskRegion::Used(used.clone()));
-                    let remaining = free.len - used.len;
-                    input.remove(index);
-                    if remaining > 0 {
-                        input.push_front(DiskRegion::Free(FreeRegion{len: remaining }))
-                    }
-                } else {
-                    output.push(front.clone())
+            DiskRegion::Free(free) => {
+                pos += free.len;
+            }
+        }
+    }
+    add_span(span_start, pos - span_start);
+
+    files.sort_by_key(|(_, file)| Reverse(file.pid));
+    for (file_pos, file) in files.iter_mut().filter(|(_, file)| file.len > 0) {
+        let leftmost_fit = (file.len..=MAX_LEN)
+            .filter_map(|bucket| free_spans[bucket].peek().map(|Reverse(span)| (*span, bucket)))
+            .min();
+        if let Some(((span_pos, span_len), bucket)) = leftmost_fit {
+            if span_pos < *file_pos {
+                free_spans[bucket].pop();
+                let remaining = span_len - file.len;
+                if remaining > 0 {
+                    free_spans[remaining.min(MAX_LEN)].push(Reverse((span_pos + file.len, remaining)));
                 }
+                *file_pos = span_pos;
             }
         }
-    };
-    return DiskMap {
-        regions: output
-    };
+    }
+
+    files.sort_by_key(|(pos, _)| *pos);
+    let mut output: Vec<DiskRegion> = Vec::new();
+    let mut pos = 0;
+    for (file_pos, file) in files {
+        if file_pos > pos {
+            output.push(DiskRegion::Free(FreeRegion { len: file_pos - pos }));
+        }
+        // Empty files can end up inside a file that was moved over their position
+        pos = pos.max(file_pos + file.len);
+        output.push(DiskRegion::Used(file));
+    }
+    DiskMap { regions: output }
 }
 
 #[test]
 fn test_compress_simple() {
     let demo_txt = read_input_file("day9", "demo.txt");
-    let demo_input = DiskMap::from_compressed_string(&demo_txt);
+    let demo_input = DiskMap::from_compressed_string(&demo_txt).expect("demo.txt should parse");
     let compacted = compress_simple(&demo_input);
     assert_eq!(compacted.checksum(), 1928usize);
 }
@@ -194,20 +462,128 @@ fn test_compress_simple() {
 #[test]
 fn test_compress_advanced() {
     let demo_txt = read_input_file("day9", "demo.txt");
-    let demo_input = DiskMap::from_compressed_string(&demo_txt);
+    let demo_input = DiskMap::from_compressed_string(&demo_txt).expect("demo.txt should parse");
     let compacted = compress_advanced(&demo_input);
     assert_eq!(compacted.checksum(), 2858usize);
 }
 
+#[cfg(test)]
+const DEMO: &str = "2333133121414131402";
+
+#[test]
+fn test_compress_demo() {
+    let demo_input = DiskMap::from_compressed_string(DEMO).expect("Demo should parse");
+    assert_eq!(compress_simple(&demo_input).checksum(), 1928);
+    assert_eq!(compress_advanced(&demo_input).checksum(), 2858);
+}
+
+/// A random dense disk map with a fixed seed
+#[cfg(test)]
+fn random_disk(seed: u64, len: usize) -> String {
+    let mut rng = crate::utils::TestRng::new(seed);
+    (0..len).map(|_| char::from(b'0' + rng.below(10) as u8)).collect()
+}
+
+/// Whole-file compaction done literally, one block at a time, as the puzzle describes it.
+/// The original queue based version filled each gap with the last file that fit, which doesn't
+/// follow the pid order and gives 2517 on the demo, so this is what the heap version is checked against
+#[cfg(test)]
+fn compress_advanced_blocks(input: &DiskMap) -> usize {
+    let mut blocks = input.to_blocks();
+    let max_pid = blocks.iter().flatten().max().copied().unwrap_or(0);
+    for pid in (0..=max_pid).rev() {
+        let Some(start) = blocks.iter().position(|b| *b == Some(pid)) else { continue };
+        let len = blocks.iter().filter(|b| **b == Some(pid)).count();
+        let target = (0..start).find(|i| blocks[*i..*i + len].iter().all(Option::is_none));
+        if let Some(target) = target.filter(|t| t + len <= start) {
+            blocks[start..start + len].fill(None);
+            blocks[target..target + len].fill(Some(pid));
+        }
+    }
+    blocks.iter().enumerate().map(|(i, b)| i * b.unwrap_or(0)).sum()
+}
+
+#[test]
+fn test_compress_matches_reference() {
+    for seed in 0..200 {
+        let disk = random_disk(seed, 1 + (seed as usize % 60));
+        let input = DiskMap::from_compressed_string(&disk).expect("Random disk should parse");
+        assert_eq!(compress_simple(&input).checksum(), compress_simple_reference(&input).checksum(), "{disk}");
+        assert_eq!(compress_advanced(&input).checksum(), compress_advanced_blocks(&input), "{disk}");
+        assert_eq!(validate_compaction(&input, &compress_simple(&input), CompactionMode::Fragmenting), Ok(()), "{disk}");
+        assert_eq!(validate_compaction(&input, &compress_advanced(&input), CompactionMode::WholeFile), Ok(()), "{disk}");
+    }
+}
+
+#[test]
+fn test_compressed_string_round_trip() {
+    let demo_input = DiskMap::from_compressed_string(&format!("{DEMO}\n")).expect("Trailing newline should be ignored");
+    assert_eq!(demo_input.to_compressed_string(), Ok(DEMO.to_string()));
+    for seed in 0..50 {
+        let disk = random_disk(seed, 1 + (seed as usize % 60));
+        let input = DiskMap::from_compressed_string(&disk).expect("Random disk should parse");
+        assert_eq!(input.to_compressed_string(), Ok(disk));
+    }
+
+    assert_eq!(DiskMap::from_compressed_string("12x4").err(), Some(ParseError::InvalidCharacter { index: 2, found: "x".to_string() }));
+    assert_eq!(DiskMap::from_compressed_string("\n 12x4").err(), Some(ParseError::InvalidCharacter { index: 4, found: "x".to_string() }));
+    // Free space before file 0 has no place in the dense format
+    let leading = DiskMap::from_block_string("..0").expect("Should parse");
+    assert_eq!(leading.to_compressed_string(), Err(FormatError::LeadingFreeSpace { len: 2 }));
+    let empty = DiskMap::from_block_string("...").expect("Should parse");
+    assert_eq!(empty.to_compressed_string(), Err(FormatError::LeadingFreeSpace { len: 3 }));
+    assert_eq!(DiskMap::from_block_string("0..").expect("Should parse").to_compressed_string(), Ok("12".to_string()));
+    let compacted = compress_simple(&demo_input);
+    assert!(matches!(compacted.to_compressed_string(), Err(FormatError::PidOutOfOrder { expected: 1, found: 9 })));
+}
+
+#[test]
+fn test_block_string() {
+    let demo_input = DiskMap::from_compressed_string(DEMO).expect("Demo should parse");
+    assert_eq!(demo_input.to_block_string(), "00...111...2...333.44.5555.6666.777.888899");
+    assert_eq!(compress_simple(&demo_input).to_block_string(), "0099811188827773336446555566");
+    assert_eq!(compress_advanced(&demo_input).to_string(), "00992111777.44.333....5555.6666.....8888");
+
+    let parsed = DiskMap::from_block_string("00992111777.44.333....5555.6666.....8888").expect("Blocks should parse");
+    assert_eq!(parsed.checksum(), 2858);
+    assert_eq!(parsed.to_block_string(), "00992111777.44.333....5555.6666.....8888");
+
+    let wide = DiskMap::from_compressed_string("1020304050607080901121").expect("Should parse");
+    assert_eq!(wide.to_block_string(), " 0  1  1  2  2  2  3  3  3  3  4  4  4  4  4  5  5  5  5  5  5  6  6  6  6  6  6  6  7  7  7  7  7  7  7  7  8  8  8  8  8  8  8  8  8  9  . 10 10  .");
+    let reparsed = DiskMap::from_block_string(&wide.to_block_string()).expect("Padded blocks should parse");
+    assert_eq!(reparsed.to_compressed_string(), Ok("1020304050607080901121".to_string()));
+
+    assert_eq!(DiskMap::from_block_string("  00x1").err(), Some(ParseError::InvalidCharacter { index: 4, found: "x".to_string() }));
+    assert_eq!(DiskMap::from_block_string(" 9  .  x 10").err(), Some(ParseError::InvalidCharacter { index: 7, found: "x".to_string() }));
+}
+
+#[test]
+fn test_validate_compaction() {
+    let before = DiskMap::from_block_string("0..111....22").expect("Should parse");
+    let check = |after: &str, mode| validate_compaction(&before, &DiskMap::from_block_string(after).expect("Should parse"), mode);
+
+    assert_eq!(check("022111......", CompactionMode::Fragmenting), Ok(()));
+    assert_eq!(check("0221112.....", CompactionMode::Fragmenting), Err(CompactionError::SizeChanged { pid: 2, before: 2, after: 3 }));
+    assert_eq!(check("02.111.2....", CompactionMode::Fragmenting), Err(CompactionError::Gap { pos: 3 }));
+
+    let before = DiskMap::from_block_string("0...11.2").expect("Should parse");
+    let check = |after: &str, mode| validate_compaction(&before, &DiskMap::from_block_string(after).expect("Should parse"), mode);
+    assert_eq!(check("0211....", CompactionMode::Fragmenting), Ok(()));
+    assert_eq!(check("0121....", CompactionMode::Fragmenting), Err(CompactionError::PidOrder { pos: 2 }));
+    assert_eq!(check("0211....", CompactionMode::WholeFile), Ok(()));
+    assert_eq!(check("0121....", CompactionMode::WholeFile), Err(CompactionError::FileSplit { pid: 1 }));
+    assert_eq!(check("0....112", CompactionMode::WholeFile), Err(CompactionError::MovedRight { pid: 1 }));
+}
+
 pub fn part1() -> usize {
     let full_txt = read_input_file("day9", "full.txt");
-    let full_input = DiskMap::from_compressed_string(&full_txt);
+    let full_input = DiskMap::from_compressed_string(&full_txt).expect("full.txt should parse");
     let compacted = compress_simple(&full_input);
     compacted.checksum()
 }
 pub fn part2() -> usize {
     let full_txt = read_input_file("day9", "full.txt");
-    let full_input = DiskMap::from_compressed_string(&full_txt);
+    let full_input = DiskMap::from_compressed_string(&full_txt).expect("full.txt should parse");
     let compacted = compress_advanced(&full_input);
     compacted.checksum()
 }
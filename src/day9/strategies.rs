@@ -0,0 +1,378 @@
+use std::collections::HashSet;
+use crate::day9::{compress_advanced, compress_simple, CompactionMode, DiskMap, DiskRegion, FreeRegion, UsedRegion};
+
+/// A way of compacting a disk, so different strategies can be compared on the same disk
+pub trait CompactionStrategy {
+    fn name(&self) -> &'static str;
+    /// Which invariants the result should satisfy, see [crate::day9::validate_compaction]
+    fn mode(&self) -> CompactionMode;
+    fn compact(&self, disk: &DiskMap) -> DiskMap;
+
+    fn run(&self, disk: &DiskMap) -> (DiskMap, CompactionStats) {
+        let compacted = self.compact(disk);
+        let stats = CompactionStats::compare(disk, &compacted, self.mode());
+        (compacted, stats)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct CompactionStats {
+    /// How many runs of blocks were moved, a whole file moved in one go counts once
+    pub moves: usize,
+    pub blocks_moved: usize,
+    /// How many extra pieces files were split into, 0 if every file is contiguous
+    pub fragmentation: usize,
+    pub checksum: usize,
+}
+
+impl CompactionStats {
+    /// Works out what moved by comparing the disk before and after.
+    /// Fragmenting compaction only ever moves blocks into free space, so any block that sits where
+    /// its file wasn't before has moved. Whole file compaction can shift a file over its old
+    /// position, so there any file that starts somewhere else has moved entirely
+    pub fn compare(before: &DiskMap, after: &DiskMap, mode: CompactionMode) -> CompactionStats {
+        let after_blocks = after.to_blocks();
+        let (moves, blocks_moved) = match mode {
+            CompactionMode::Fragmenting => {
+                let before_blocks = before.to_blocks();
+                let moved = |pos: usize| after_blocks[pos].is_some() && before_blocks.get(pos) != Some(&after_blocks[pos]);
+                let blocks_moved = (0..after_blocks.len()).filter(|pos| moved(*pos)).count();
+                let moves = (0..after_blocks.len())
+                    .filter(|pos| moved(*pos) && (*pos == 0 || !moved(pos - 1) || after_blocks[pos - 1] != after_blocks[*pos]))
+                    .count();
+                (moves, blocks_moved)
+            }
+            CompactionMode::WholeFile | CompactionMode::Packed => {
+                let mut before_files = file_positions(before);
+                let mut after_files = file_positions(after);
+                before_files.sort_by_key(|(_, file)| file.pid);
+                after_files.sort_by_key(|(_, file)| file.pid);
+                before_files.iter().zip(after_files.iter())
+                    .filter(|((before_pos, file), (after_pos, _))| file.len > 0 && before_pos != after_pos)
+                    .fold((0, 0), |(moves, blocks), ((_, file), _)| (moves + 1, blocks + file.len))
+            }
+        };
+
+        let mut pieces: Vec<usize> = Vec::new();
+        for (pos, block) in after_blocks.iter().enumerate() {
+            if let Some(pid) = block {
+                if pos == 0 || after_blocks[pos - 1] != *block {
+                    if pieces.len() <= *pid {
+                        pieces.resize(pid + 1, 0);
+                    }
+                    pieces[*pid] += 1;
+                }
+            }
+        }
+        let fragmentation = pieces.iter().map(|p| p.saturating_sub(1)).sum();
+
+        CompactionStats { moves, blocks_moved, fragmentation, checksum: after.checksum() }
+    }
+}
+
+/// Moves single blocks from the end into the first free block, splitting files (part 1)
+pub struct Fragmenting;
+impl CompactionStrategy for Fragmenting {
+    fn name(&self) -> &'static str { "fragmenting" }
+    fn mode(&self) -> CompactionMode { CompactionMode::Fragmenting }
+    fn compact(&self, disk: &DiskMap) -> DiskMap { compress_simple(disk) }
+}
+
+/// Moves whole files, highest pid first, into the leftmost span that fits (part 2)
+pub struct FirstFit;
+impl CompactionStrategy for FirstFit {
+    fn name(&self) -> &'static str { "first fit" }
+    fn mode(&self) -> CompactionMode { CompactionMode::WholeFile }
+    fn compact(&self, disk: &DiskMap) -> DiskMap { compress_advanced(disk) }
+}
+
+/// Moves whole files, highest pid first, into the smallest span to their left that fits.
+/// Ties go to the leftmost span
+pub struct BestFit;
+impl CompactionStrategy for BestFit {
+    fn name(&self) -> &'static str { "best fit" }
+    fn mode(&self) -> CompactionMode { CompactionMode::WholeFile }
+    fn compact(&self, disk: &DiskMap) -> DiskMap {
+        compact_whole_files(disk, |spans, len| {
+            spans.iter().enumerate()
+                .filter(|(_, (_, span_len))| *span_len >= len)
+                .min_by_key(|(_, (pos, span_len))| (*span_len, *pos))
+                .map(|(i, _)| i)
+        })
+    }
+}
+
+/// Moves whole files, highest pid first, into the largest span to their left that fits.
+/// Ties go to the leftmost span
+pub struct WorstFit;
+impl CompactionStrategy for WorstFit {
+    fn name(&self) -> &'static str { "worst fit" }
+    fn mode(&self) -> CompactionMode { CompactionMode::WholeFile }
+    fn compact(&self, disk: &DiskMap) -> DiskMap {
+        compact_whole_files(disk, |spans, len| {
+            spans.iter().enumerate()
+                .filter(|(_, (_, span_len))| *span_len >= len)
+                .min_by_key(|(_, (pos, span_len))| (usize::MAX - *span_len, *pos))
+                .map(|(i, _)| i)
+        })
+    }
+}
+
+/// Packs every file into one contiguous run at the start of the disk, without splitting any file.
+///
+/// Files that already lie inside the packed area stay put, and the gaps between them are filled
+/// exactly with the files from beyond it. Those files have to move no matter what, so this takes
+/// the fewest moves possible. Which files go into which gap is chosen for all gaps together, see
+/// `GapFiller`. Only if there is no way to fill every gap exactly is every file slid left in order
+/// instead
+pub struct Defragment;
+impl CompactionStrategy for Defragment {
+    fn name(&self) -> &'static str { "defragment" }
+    fn mode(&self) -> CompactionMode { CompactionMode::Packed }
+    fn compact(&self, disk: &DiskMap) -> DiskMap {
+        let files = file_positions(disk);
+        let used: usize = files.iter().map(|(_, file)| file.len).sum();
+
+        let (mut placed, movers): (Vec<_>, Vec<_>) = files.into_iter()
+            .partition(|(pos, file)| pos + file.len <= used);
+        let (empty, mut movers): (Vec<_>, Vec<_>) = movers.into_iter().map(|(_, file)| file).partition(|file| file.len == 0);
+        // Longest first, and within a length the highest pid is popped first
+        movers.sort_by_key(|file| (std::cmp::Reverse(file.len), file.pid));
+        let mut lengths: Vec<usize> = movers.iter().map(|file| file.len).collect();
+        lengths.dedup();
+        let mut by_length: Vec<Vec<UsedRegion>> = lengths.iter()
+            .map(|len| movers.iter().filter(|file| file.len == *len).cloned().collect())
+            .collect();
+
+        let gaps = free_spans(&placed, used);
+        let mut filler = GapFiller {
+            gaps: gaps.iter().map(|(_, len)| *len).collect(),
+            counts: by_length.iter().map(Vec::len).collect(),
+            lengths,
+            failed: HashSet::new(),
+            chosen: Vec::new(),
+        };
+        if !filler.fill(0) {
+            return slide_left(disk);
+        }
+        for ((gap_pos, _), chosen) in gaps.into_iter().zip(filler.chosen) {
+            let mut files: Vec<UsedRegion> = chosen.into_iter()
+                .map(|length| by_length[length].pop().expect("Only available lengths are chosen"))
+                .collect();
+            files.sort_by_key(|file| file.pid);
+            let mut pos = gap_pos;
+            for file in files {
+                let len = file.len;
+                placed.push((pos, file));
+                pos += len;
+            }
+        }
+        // Empty files can go anywhere
+        placed.extend(empty.into_iter().map(|file| (used, file)));
+        to_disk_map(placed)
+    }
+}
+
+/// Every file with its start position, in disk order
+fn file_positions(disk: &DiskMap) -> Vec<(usize, UsedRegion)> {
+    let mut files = Vec::new();
+    let mut pos = 0;
+    for region in disk.regions.iter() {
+        match region {
+            DiskRegion::Used(used) => {
+                files.push((pos, used.clone()));
+                pos += used.len;
+            }
+            DiskRegion::Free(free) => pos += free.len,
+        }
+    }
+    files
+}
+
+/// (position, length) of every run of free blocks before `end`, given where the files are
+fn free_spans(files: &[(usize, UsedRegion)], end: usize) -> Vec<(usize, usize)> {
+    let mut occupied: Vec<(usize, usize)> = files.iter().map(|(pos, file)| (*pos, pos + file.len)).collect();
+    occupied.sort();
+    let mut spans = Vec::new();
+    let mut pos = 0;
+    for (start, stop) in occupied {
+        if start > pos {
+            spans.push((pos, start - pos));
+        }
+        pos = pos.max(stop);
+    }
+    if end > pos {
+        spans.push((pos, end - pos));
+    }
+    spans
+}
+
+fn to_disk_map(mut files: Vec<(usize, UsedRegion)>) -> DiskMap {
+    files.sort_by_key(|(pos, file)| (*pos, file.len));
+    let mut regions = Vec::new();
+    let mut pos = 0;
+    for (file_pos, file) in files {
+        if file_pos > pos {
+            regions.push(DiskRegion::Free(FreeRegion { len: file_pos - pos }));
+        }
+        pos = pos.max(file_pos + file.len);
+        regions.push(DiskRegion::Used(file));
+    }
+    DiskMap { regions }
+}
+
+/// Whole-file compaction, highest pid first, where `choose` picks which of the free spans
+/// `(position, length)` left of the file it goes into, given the file length
+fn compact_whole_files(disk: &DiskMap, choose: impl Fn(&[(usize, usize)], usize) -> Option<usize>) -> DiskMap {
+    let mut files = file_positions(disk);
+    let mut spans = free_spans(&files, files.last().map_or(0, |(pos, file)| pos + file.len));
+    files.sort_by_key(|(_, file)| std::cmp::Reverse(file.pid));
+    for (file_pos, file) in files.iter_mut().filter(|(_, file)| file.len > 0) {
+        let left_of_file = spans.partition_point(|(pos, _)| pos < file_pos);
+        if let Some(index) = choose(&spans[..left_of_file], file.len) {
+            let (span_pos, span_len) = spans[index];
+            *file_pos = span_pos;
+            if span_len == file.len {
+                spans.remove(index);
+            } else {
+                spans[index] = (span_pos + file.len, span_len - file.len);
+            }
+        }
+    }
+    to_disk_map(files)
+}
+
+/// Splits the files over the gaps so that every gap is filled exactly, with backtracking.
+///
+/// Files of the same length are interchangeable, so only how many of each length are left
+/// matters. A gap that couldn't be filled from some set of leftover files never can be, which is
+/// remembered so that no combination is tried twice
+struct GapFiller {
+    /// Length of every gap, in disk order
+    gaps: Vec<usize>,
+    /// Every file length, longest first
+    lengths: Vec<usize>,
+    /// How many files of each length are left
+    counts: Vec<usize>,
+    /// (gap, counts) from which the remaining gaps can't be filled
+    failed: HashSet<(usize, Vec<usize>)>,
+    /// For every gap filled so far, indices into `lengths` of the files that went into it
+    chosen: Vec<Vec<usize>>,
+}
+impl GapFiller {
+    /// Fills the given gap and all after it, or leaves `chosen` as it was and returns false
+    fn fill(&mut self, gap: usize) -> bool {
+        if gap == self.gaps.len() {
+            return self.counts.iter().all(|count| *count == 0);
+        }
+        if self.failed.contains(&(gap, self.counts.clone())) {
+            return false;
+        }
+        self.chosen.push(Vec::new());
+        if self.fill_with(gap, 0, self.gaps[gap]) {
+            return true;
+        }
+        self.chosen.pop();
+        self.failed.insert((gap, self.counts.clone()));
+        false
+    }
+
+    /// Fills what's left of the gap with files of the given length or shorter, as many of the
+    /// longest as possible first, then moves on to the next gap
+    fn fill_with(&mut self, gap: usize, length: usize, remaining: usize) -> bool {
+        if remaining == 0 {
+            return self.fill(gap + 1);
+        }
+        if length == self.lengths.len() {
+            return false;
+        }
+        let most = self.counts[length].min(remaining / self.lengths[length]);
+        for count in (0..=most).rev() {
+            self.counts[length] -= count;
+            self.chosen[gap].extend(std::iter::repeat_n(length, count));
+            if self.fill_with(gap, length + 1, remaining - count * self.lengths[length]) {
+                return true;
+            }
+            let kept = self.chosen[gap].len() - count;
+            self.chosen[gap].truncate(kept);
+            self.counts[length] += count;
+        }
+        false
+    }
+}
+
+/// Packs every file to the left, keeping their order on the disk
+fn slide_left(disk: &DiskMap) -> DiskMap {
+    DiskMap {
+        regions: file_positions(disk).into_iter().map(|(_, file)| DiskRegion::Used(file)).collect(),
+    }
+}
+
+#[cfg(test)]
+fn all_strategies() -> Vec<Box<dyn CompactionStrategy>> {
+    vec![Box::new(Fragmenting), Box::new(FirstFit), Box::new(BestFit), Box::new(WorstFit), Box::new(Defragment)]
+}
+
+#[test]
+fn test_strategies_on_demo() {
+    let demo = DiskMap::from_compressed_string("2333133121414131402").expect("Demo should parse");
+    let results: Vec<(&str, String, CompactionStats)> = all_strategies().iter()
+        .map(|strategy| {
+            let (compacted, stats) = strategy.run(&demo);
+            (strategy.name(), compacted.to_block_string(), stats)
+        })
+        .collect();
+
+    assert_eq!(results[0].1, "0099811188827773336446555566");
+    assert_eq!(results[0].2, CompactionStats { moves: 7, blocks_moved: 12, fragmentation: 3, checksum: 1928 });
+    assert_eq!(results[1].1, "00992111777.44.333....5555.6666.....8888");
+    assert_eq!(results[1].2, CompactionStats { moves: 4, blocks_moved: 8, fragmentation: 0, checksum: 2858 });
+    // No way of splitting the files beyond the packed area over the demo gaps fills them all exactly
+    assert_eq!(results[4].1, "0011123334455556666777888899");
+    assert_eq!(results[4].2, CompactionStats { moves: 9, blocks_moved: 26, fragmentation: 0, checksum: 2453 });
+}
+
+#[test]
+fn test_strategies_are_valid_compactions() {
+    use crate::day9::validate_compaction;
+    for seed in 0..100 {
+        let disk = crate::day9::random_disk(seed, 1 + (seed as usize % 60));
+        let input = DiskMap::from_compressed_string(&disk).expect("Random disk should parse");
+        for strategy in all_strategies() {
+            let (compacted, stats) = strategy.run(&input);
+            assert_eq!(validate_compaction(&input, &compacted, strategy.mode()), Ok(()), "{} on {disk}", strategy.name());
+            assert_eq!(stats.checksum, compacted.checksum());
+            if strategy.mode() != CompactionMode::Fragmenting {
+                assert_eq!(stats.fragmentation, 0, "{} on {disk}", strategy.name());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_fit_strategies_differ() {
+    let disk = DiskMap::from_block_string("0..1.2...3").expect("Should parse");
+    let compacted: Vec<String> = all_strategies().iter().map(|strategy| strategy.compact(&disk).to_string()).collect();
+    assert_eq!(compacted, vec!["0321", "0321", "021.3", "021...3", "0231"]);
+    // 0 and 1 are already inside the packed area, so only the two files after it have to move
+    assert_eq!(Defragment.run(&disk).1, CompactionStats { moves: 2, blocks_moved: 2, fragmentation: 0, checksum: 11 });
+}
+
+#[test]
+fn test_defragment_falls_back_to_sliding() {
+    // The gap of 2 can't be filled by the file of 3 beyond it
+    let disk = DiskMap::from_block_string("0..111222").expect("Should parse");
+    let (compacted, stats) = Defragment.run(&disk);
+    assert_eq!(compacted.to_block_string(), "0111222");
+    assert_eq!(stats.moves, 2);
+    assert_eq!(stats.blocks_moved, 6);
+
+    // 3 + 3 would fill the first gap, but leave 2s that can't fill the gaps of 3, so the 2s have
+    // to go into the first gap instead
+    let disk = DiskMap::from_block_string("0......1...2...3445566777888").expect("Should parse");
+    let (compacted, stats) = Defragment.run(&disk);
+    assert_eq!(compacted.to_block_string(), "0445566188827773");
+    // Only the five files beyond the packed area move
+    assert_eq!(stats.moves, 5);
+    assert_eq!(stats.blocks_moved, 12);
+}
@@ -1,7 +1,11 @@
-mod utils;
-use AoC2024::{day20};
+use AoC2024::{day8, day20};
+use AoC2024::utils::heatmap::Style;
 
 fn main() {
+    if std::env::args().any(|arg| arg == "--day8-heatmap") {
+        print!("{}", day8::heatmap(day8::Resonance::Harmonic, Style::Ansi));
+        return;
+    }
     println!("part1: {}", day20::part1());
     println!("part2: {}", day20::part2());
 }
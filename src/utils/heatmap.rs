@@ -0,0 +1,114 @@
+use std::fmt::{Display, Write};
+
+const GRAY: &str = "\x1b[90m";
+const GREEN: &str = "\x1b[92m";
+const YELLOW: &str = "\x1b[93m";
+const RED: &str = "\x1b[91m";
+const PURPLE: &str = "\x1b[95m";
+const RESET: &str = "\x1b[0m";
+
+/// Cells that render as this are considered empty, and get drawn as a shaded block in colour mode
+const BLANK: &str = ".";
+const SHADE: char = '░';
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Style {
+    /// Each cell is drawn in a colour depending on its count, for viewing in a terminal
+    Ansi,
+    /// Counts are drawn as digits over the cells, for logs and tests
+    Plain,
+}
+
+/// A grid of cells with a number attached to each cell, e.g. how many antinodes landed on it
+///
+/// Cells are stored row by row, left to right and top to bottom, the same way every `Map2D` does
+pub struct HeatMap<'a, C: Display> {
+    cells: &'a [C],
+    counts: &'a [usize],
+    width: usize,
+}
+
+impl<'a, C: Display> HeatMap<'a, C> {
+    pub fn new(cells: &'a [C], counts: &'a [usize], width: usize) -> Self {
+        assert_eq!(cells.len(), counts.len(), "Every cell needs exactly one count");
+        assert!(width > 0 && cells.len().is_multiple_of(width), "Cells should form a rectangle");
+        HeatMap { cells, counts, width }
+    }
+
+    pub fn render(&self, style: Style) -> String {
+        let mut out = String::new();
+        for (row_cells, row_counts) in self.cells.chunks(self.width).zip(self.counts.chunks(self.width)) {
+            for (cell, count) in row_cells.iter().zip(row_counts) {
+                match style {
+                    Style::Ansi => Self::write_ansi(&mut out, cell, *count),
+                    Style::Plain => Self::write_plain(&mut out, cell, *count),
+                }
+            }
+            out.push('\n');
+        }
+        out.push('\n');
+        out.push_str(&legend(style));
+        out
+    }
+
+    fn write_ansi(out: &mut String, cell: &C, count: usize) {
+        let glyph = cell.to_string();
+        let colour = colour(count);
+        if glyph == BLANK {
+            write!(out, "{colour}{SHADE}{RESET} ").unwrap();
+        } else if count == 0 {
+            write!(out, "{glyph} ").unwrap();
+        } else {
+            write!(out, "{colour}{glyph}{RESET} ").unwrap();
+        }
+    }
+
+    fn write_plain(out: &mut String, cell: &C, count: usize) {
+        match count {
+            0 => write!(out, "{cell}").unwrap(),
+            1..=9 => write!(out, "{count}").unwrap(),
+            _ => out.push('#'),
+        }
+    }
+}
+
+fn colour(count: usize) -> &'static str {
+    match count {
+        0 => GRAY,
+        1 => GREEN,
+        2 => YELLOW,
+        3 => RED,
+        _ => PURPLE,
+    }
+}
+
+fn legend(style: Style) -> String {
+    match style {
+        Style::Ansi => format!(
+            "{GRAY}{SHADE}{RESET} 0  {GREEN}{SHADE}{RESET} 1  {YELLOW}{SHADE}{RESET} 2  {RED}{SHADE}{RESET} 3  {PURPLE}{SHADE}{RESET} 4+\n"
+        ),
+        Style::Plain => String::from("cell = 0, 1-9 = count, # = 10+\n"),
+    }
+}
+
+#[test]
+fn test_render_plain() {
+    let cells = ['.', 'a', '.', '.', '.', 'a'];
+    let counts = [0, 0, 1, 2, 12, 1];
+    let rendered = HeatMap::new(&cells, &counts, 3).render(Style::Plain);
+    assert_eq!(rendered, ".a1\n2#1\n\ncell = 0, 1-9 = count, # = 10+\n");
+}
+#[test]
+fn test_render_ansi() {
+    let cells = ['.', 'a', 'a'];
+    let counts = [1, 0, 3];
+    let rendered = HeatMap::new(&cells, &counts, 3).render(Style::Ansi);
+    let first_line = rendered.lines().next().unwrap();
+    assert_eq!(first_line, format!("{GREEN}░{RESET} a {RED}a{RESET} "));
+    assert!(rendered.ends_with(&legend(Style::Ansi)));
+}
+#[test]
+#[should_panic]
+fn test_counts_must_match_cells() {
+    HeatMap::new(&['.', '.'], &[0], 2);
+}
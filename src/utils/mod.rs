@@ -1,3 +1,5 @@
+pub mod heatmap;
+
 use std::fs;
 use std::path::Path;
 
@@ -27,6 +29,34 @@ pub fn read_input_files(day: &str) -> InputFiles{
     }
 }
 
+/// Greatest common divisor, gcd(0, 0) is 0
+pub fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Xorshift generator for tests that build random inputs, the same ones on every run
+#[cfg(test)]
+pub struct TestRng {
+    state: u64,
+}
+#[cfg(test)]
+impl TestRng {
+    /// The seed is mixed first, so small seeds such as loop counters give unrelated streams
+    pub fn new(seed: u64) -> TestRng {
+        TestRng { state: seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407) | 1 }
+    }
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+    /// A number in `0..n`
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}
+
 /** https://stackoverflow.com/questions/13322479/how-to-benchmark-programs-in-rust */
 pub struct Timer(&'static str, std::time::SystemTime);
 impl Drop for Timer {